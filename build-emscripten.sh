@@ -18,7 +18,7 @@ cp node_modules/promise-polyfill/promise.js ${OUTPUT_DIR}
 
 echo "Building for ${WASM32_TARGET}..."
 
-cargo build --target=${WASM32_TARGET}
+cargo build --target=${WASM32_TARGET} --bin werewolves-and-wanderer
 
 cp ${WASM32_ROOT_DIR}/werewolves-and-wanderer.js ${OUTPUT_DIR}
 cp ${WASM32_ROOT_DIR}/deps/werewolves_and_wanderer*.wasm ${OUTPUT_DIR}
@@ -28,6 +28,6 @@ export ASMJS_ROOT_DIR=target/${ASMJS_TARGET}/debug
 
 echo "Building for ${ASMJS_TARGET}..."
 
-cargo build --target=${ASMJS_TARGET}
+cargo build --target=${ASMJS_TARGET} --bin werewolves-and-wanderer
 
 cp ${ASMJS_ROOT_DIR}/werewolves-and-wanderer.js ${OUTPUT_DIR}/werewolves-and-wanderer.asm.js
@@ -24,6 +24,19 @@ interface Window {
   // Called from Rust code when the program has terminated.
   terminate_program: () => void;
 
+  // Called from Rust code when it wants to persist some data (e.g. a
+  // saved game) across visits to the page. Returns 1 on success and
+  // 0 on failure, e.g. if the browser has disabled storage.
+  save_data: (key: string, data: string) => number;
+
+  // Called from Rust code when it wants to know if any data has been
+  // persisted under the given key. Returns 1 if so, 0 otherwise.
+  has_data: (key: string) => number;
+
+  // Called from Rust code when it wants to retrieve persisted data.
+  // It should only be called when has_data() returns 1.
+  load_data: (key: string) => string;
+
   // This is part of Emscripten's API. For more details, see:
   // http://kripken.github.io/emscripten-site/docs/api_reference/module.html
   Module: {
@@ -141,6 +154,36 @@ interface Window {
     });
   };
 
+  function storage_key(key: string): string {
+    return `werewolves-and-wanderer:${key}`;
+  }
+
+  window.save_data = (key, data) => {
+    try {
+      window.localStorage.setItem(storage_key(key), data);
+      return 1;
+    } catch (e) {
+      return 0;
+    }
+  };
+
+  window.has_data = key => {
+    try {
+      return window.localStorage.getItem(storage_key(key)) === null ? 0 : 1;
+    } catch (e) {
+      return 0;
+    }
+  };
+
+  window.load_data = key => {
+    const data = window.localStorage.getItem(storage_key(key));
+    if (data === null) {
+      throw new Error("Assertion failure: load_data() should only " +
+                      "be called when has_data() returns 1.");
+    }
+    return data;
+  };
+
   window.Module = {
     print(msg: string) {
       _currentPromise.then(() => {
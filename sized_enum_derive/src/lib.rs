@@ -0,0 +1,126 @@
+// Derives the `SizedEnum` trait from the main crate for enums whose
+// variants have no fields, e.g.:
+//
+//   #[derive(SizedEnum)]
+//   pub enum Direction {
+//     North,
+//     South,
+//   }
+//
+// Variants are numbered in the order they're declared, starting from
+// 0, so the size of the enum can never fall out of date with its
+// variants. The generated impl refers to the trait as `SizedEnum`, so
+// it needs to be in scope wherever the derive is used.
+//
+// This only needs to understand very simple enums, so it picks apart
+// the tokens by hand rather than pulling in a full Rust parser.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+#[proc_macro_derive(SizedEnum)]
+pub fn derive_sized_enum(input: TokenStream) -> TokenStream {
+  let code = match parse_enum(input) {
+    Ok((name, variants)) => generate_impl(&name, &variants),
+    Err(message) => format!("compile_error!({:?});", message),
+  };
+  code.parse().expect("Generated invalid code for SizedEnum")
+}
+
+// Returns the name of the enum and the names of its variants.
+fn parse_enum(input: TokenStream) -> Result<(String, Vec<String>), String> {
+  let mut tokens = input.into_iter();
+
+  // Skip past any attributes and visibility to the `enum` keyword.
+  loop {
+    match tokens.next() {
+      Some(TokenTree::Ident(ref ident)) if ident.to_string() == "enum" => {
+        break;
+      },
+      Some(TokenTree::Ident(ref ident))
+          if ident.to_string() == "struct" || ident.to_string() == "union" => {
+        return Err(String::from("SizedEnum can only be derived for enums"));
+      },
+      Some(_) => {},
+      None => { return Err(String::from("Expected an enum")); },
+    }
+  }
+
+  let name = match tokens.next() {
+    Some(TokenTree::Ident(ident)) => ident.to_string(),
+    _ => { return Err(String::from("Expected the name of the enum")); },
+  };
+  let body = match tokens.next() {
+    Some(TokenTree::Group(ref group))
+        if group.delimiter() == Delimiter::Brace => group.stream(),
+    _ => {
+      return Err(format!("SizedEnum can't be derived for generic enums \
+                          like {}", name));
+    },
+  };
+
+  let mut variants: Vec<String> = Vec::new();
+  let mut expecting_variant = true;
+  for token in body {
+    match token {
+      // Attributes and doc comments on variants, e.g. `#[doc = "..."]`.
+      TokenTree::Punct(ref punct)
+          if punct.as_char() == '#' && expecting_variant => {},
+      TokenTree::Group(ref group)
+          if group.delimiter() == Delimiter::Bracket && expecting_variant => {},
+      TokenTree::Ident(ident) if expecting_variant => {
+        variants.push(ident.to_string());
+        expecting_variant = false;
+      },
+      TokenTree::Punct(ref punct)
+          if punct.as_char() == ',' && !expecting_variant => {
+        expecting_variant = true;
+      },
+      _ => {
+        return Err(format!("Variant {}::{} must not have fields or an \
+                            explicit discriminant", name,
+                           variants.last().map_or("?", |v| v.as_str())));
+      },
+    }
+  }
+
+  Ok((name, variants))
+}
+
+fn generate_impl(name: &str, variants: &[String]) -> String {
+  let mut from_usize = String::new();
+  let mut to_usize = String::new();
+  let mut names = String::new();
+  for (i, variant) in variants.iter().enumerate() {
+    from_usize += &format!("{} => Some({}::{}),", i, name, variant);
+    to_usize += &format!("{}::{} => {},", name, variant, i);
+    names += &format!("{}::{} => {:?},", name, variant, variant);
+  }
+
+  // An enum without variants can't be matched on by value, but then
+  // there's never a value to call these with either.
+  if variants.is_empty() {
+    to_usize = String::from("_ => unreachable!(),");
+    names = to_usize.clone();
+  }
+
+  format!("
+    impl SizedEnum for {name} {{
+      fn size() -> usize {{ {size} }}
+
+      fn from_usize(index: usize) -> Option<Self> {{
+        match index {{ {from_usize} _ => None }}
+      }}
+
+      fn to_usize(self) -> usize {{
+        match self {{ {to_usize} }}
+      }}
+
+      fn name(self) -> &'static str {{
+        match self {{ {names} }}
+      }}
+    }}
+  ", name = name, size = variants.len(), from_usize = from_usize,
+     to_usize = to_usize, names = names)
+}
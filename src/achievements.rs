@@ -0,0 +1,231 @@
+use map::{Map, RoomId};
+use items::ItemId;
+use monsters::MonsterId;
+use events::GameEvent;
+use game_state::GameState;
+
+use self::Achievement::*;
+use self::Fact::*;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Achievement {
+  EscapeArtist,
+  DragonSlayer,
+  DualWielder,
+  HonestAdventurer,
+  Explorer,
+}
+
+const ALL_ACHIEVEMENTS: [Achievement; 5] = [
+  EscapeArtist, DragonSlayer, DualWielder, HonestAdventurer, Explorer,
+];
+
+impl Achievement {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      EscapeArtist => "Escape artist",
+      DragonSlayer => "Dragon slayer",
+      DualWielder => "Dual wielder",
+      HonestAdventurer => "Honest adventurer",
+      Explorer => "Explorer",
+    }
+  }
+
+  pub fn desc(&self) -> &'static str {
+    match *self {
+      EscapeArtist => "escape the castle without ever buying a torch",
+      DragonSlayer => "defeat the ice-dragon",
+      DualWielder => "own an axe and a sword at the same time",
+      HonestAdventurer => "escape the castle without being accused of \
+                           cheating",
+      Explorer => "visit every room in the castle",
+    }
+  }
+}
+
+// Something that has happened so far which achievements depend on.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Fact {
+  BoughtTorch,
+  HasAxe,
+  HasSword,
+  DualWielded,
+  KilledDragon,
+  AccusedOfCheating,
+  Escaped,
+}
+
+const ALL_FACTS: [Fact; 7] = [
+  BoughtTorch, HasAxe, HasSword, DualWielded, KilledDragon,
+  AccusedOfCheating, Escaped,
+];
+
+impl Fact {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      BoughtTorch => "bought_torch",
+      HasAxe => "has_axe",
+      HasSword => "has_sword",
+      DualWielded => "dual_wielded",
+      KilledDragon => "killed_dragon",
+      AccusedOfCheating => "accused_of_cheating",
+      Escaped => "escaped",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Fact> {
+    ALL_FACTS.iter().find(|fact| fact.name() == name).cloned()
+  }
+}
+
+// Keeps track of the game's events in order to figure out which
+// achievements the player has earned.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AchievementTracker {
+  facts: Vec<Fact>,
+  entered: Vec<bool>,
+
+  // Castles without a dragon can't have dragon slayers, and shops
+  // without swords can't make dual wielders.
+  dragon: Option<MonsterId>,
+  item_facts: Vec<(ItemId, Fact)>,
+}
+
+impl AchievementTracker {
+  // The player starts out at the map's entrance, so it counts as
+  // having been entered.
+  pub fn new(map: &Map) -> Self {
+    let mut tracker = Self {
+      facts: Vec::new(),
+      entered: vec![false; map.num_rooms()],
+      dragon: map.monsters.find("dragon"),
+      item_facts: [
+        ("torch", BoughtTorch), ("axe", HasAxe), ("sword", HasSword),
+      ].iter().filter_map(|&(key, fact)| {
+        map.catalogue.find(key).map(|id| (id, fact))
+      }).collect(),
+    };
+    tracker.enter(map.entrance);
+    tracker
+  }
+
+  pub fn knows(&self, fact: Fact) -> bool {
+    self.facts.contains(&fact)
+  }
+
+  pub fn learn(&mut self, fact: Fact) {
+    if !self.knows(fact) {
+      self.facts.push(fact);
+    }
+  }
+
+  fn forget(&mut self, fact: Fact) {
+    self.facts.retain(|&known| known != fact);
+  }
+
+  pub fn facts(&self) -> &[Fact] {
+    &self.facts
+  }
+
+  pub fn enter(&mut self, r: RoomId) {
+    self.entered[r.0] = true;
+  }
+
+  pub fn entered_rooms<'a>(&'a self) -> impl Iterator<Item = RoomId> + 'a {
+    self.entered.iter().enumerate()
+      .filter(|&(_, &entered)| entered)
+      .map(|(i, _)| RoomId(i))
+  }
+
+  pub fn observe(&mut self, event: &GameEvent) {
+    match *event {
+      GameEvent::RoomEntered(r) => { self.enter(r) },
+      GameEvent::ItemBought(id, _) | GameEvent::ItemFound(id, _) => {
+        let fact = self.item_facts.iter().find(|&&(item, _)| item == id);
+        if let Some(&(_, fact)) = fact {
+          self.learn(fact);
+        }
+      },
+      GameEvent::CombatWon(monster) if Some(monster) == self.dragon => {
+        self.learn(KilledDragon)
+      },
+      GameEvent::CheatingAccused => {
+        // Cheaters lose all their weapons.
+        self.learn(AccusedOfCheating);
+        self.forget(HasAxe);
+        self.forget(HasSword);
+      },
+      GameEvent::Escaped => { self.learn(Escaped) },
+      _ => {},
+    }
+    if self.knows(HasAxe) && self.knows(HasSword) {
+      self.learn(DualWielded);
+    }
+  }
+
+  pub fn has_earned(&self, achievement: Achievement) -> bool {
+    match achievement {
+      EscapeArtist => self.knows(Escaped) && !self.knows(BoughtTorch),
+      DragonSlayer => self.knows(KilledDragon),
+      DualWielder => self.knows(DualWielded),
+      HonestAdventurer => {
+        self.knows(Escaped) && !self.knows(AccusedOfCheating)
+      },
+      Explorer => self.entered.iter().all(|&entered| entered),
+    }
+  }
+
+  pub fn earned(&self) -> Vec<Achievement> {
+    ALL_ACHIEVEMENTS.iter().cloned().filter(|&achievement| {
+      self.has_earned(achievement)
+    }).collect()
+  }
+}
+
+impl GameState {
+  pub fn print_achievements(&mut self) {
+    let earned = self.achievements.earned();
+    if earned.is_empty() {
+      wrapln!(self, "You didn't earn any achievements.\n");
+      return;
+    }
+    wrapln!(self, "Achievements earned:\n");
+    for achievement in earned {
+      wrapln!(self, "  {} - {}", achievement.name(), achievement.desc());
+    }
+    wrapln!(self);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use map::Map;
+
+  #[test]
+  fn test_achievements_follow_events() {
+    let map = Map::castle();
+    let mut tracker = AchievementTracker::new(&map);
+    let axe = map.catalogue.find("axe").unwrap();
+    let sword = map.catalogue.find("sword").unwrap();
+    tracker.observe(&GameEvent::ItemBought(axe, 1));
+    tracker.observe(&GameEvent::CheatingAccused);
+    tracker.observe(&GameEvent::ItemBought(sword, 1));
+    assert!(!tracker.has_earned(DualWielder));
+
+    tracker.observe(&GameEvent::ItemBought(axe, 1));
+    tracker.observe(&GameEvent::CombatWon(map.monsters.find("werewolf")
+                                          .unwrap()));
+    assert!(!tracker.has_earned(DragonSlayer));
+    tracker.observe(&GameEvent::CombatWon(map.monsters.find("dragon")
+                                          .unwrap()));
+    tracker.observe(&GameEvent::Escaped);
+    assert_eq!(tracker.earned(), vec![EscapeArtist, DragonSlayer,
+                                      DualWielder]);
+
+    for room_id in map.room_ids() {
+      tracker.observe(&GameEvent::RoomEntered(room_id));
+    }
+    assert!(tracker.has_earned(Explorer));
+  }
+}
@@ -0,0 +1,331 @@
+use std::collections::{HashMap, VecDeque};
+
+use direction::Direction;
+use direction::Direction::*;
+use sized_enum::SizedEnum;
+use map::{Map, RoomId};
+use game_state::GameState;
+
+// Keeps track of which rooms the player has been to and which exits
+// they've noticed along the way, so we can draw them a map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exploration {
+  visited: Vec<bool>,
+  seen_exits: Vec<bool>,
+}
+
+impl Exploration {
+  pub fn new(num_rooms: usize) -> Self {
+    Self {
+      visited: vec![false; num_rooms],
+      seen_exits: vec![false; num_rooms * Direction::size()],
+    }
+  }
+
+  pub fn visit(&mut self, r: RoomId) {
+    self.visited[r.0] = true;
+  }
+
+  pub fn has_visited(&self, r: RoomId) -> bool {
+    self.visited[r.0]
+  }
+
+  pub fn see_exit(&mut self, r: RoomId, d: Direction) {
+    self.seen_exits[r.0 * Direction::size() + d as usize] = true;
+  }
+
+  pub fn has_seen_exit(&self, r: RoomId, d: Direction) -> bool {
+    self.seen_exits[r.0 * Direction::size() + d as usize]
+  }
+
+  pub fn see_all_exits(&mut self, map: &Map, r: RoomId) {
+    for dir in Direction::iter() {
+      if map.room(r).get_exit(dir).is_some() {
+        self.see_exit(r, dir);
+      }
+    }
+  }
+
+  pub fn visited_rooms<'a>(&'a self) -> impl Iterator<Item = RoomId> + 'a {
+    self.visited.iter().enumerate()
+      .filter(|&(_, &visited)| visited)
+      .map(|(i, _)| RoomId(i))
+  }
+}
+
+type Position = (i32, i32, i32);
+
+fn step((x, y, z): Position, d: Direction) -> Position {
+  match d {
+    North => (x, y - 1, z),
+    South => (x, y + 1, z),
+    East => (x + 1, y, z),
+    West => (x - 1, y, z),
+    Up => (x, y, z + 1),
+    Down => (x, y, z - 1),
+  }
+}
+
+// Where each explored room goes on the map. Rooms are laid out on a
+// grid by following the exits the player has seen, starting from the
+// entrance. The castle isn't always geometrically consistent, so a
+// room that would land on top of another one just isn't placed, and
+// neither is anywhere the player got to without walking (e.g. via
+// the magic amulet).
+struct Layout {
+  positions: HashMap<RoomId, Position>,
+  rooms: HashMap<Position, RoomId>,
+}
+
+impl Layout {
+  fn new(map: &Map, explored: &Exploration) -> Self {
+    let mut layout = Self {
+      positions: HashMap::new(),
+      rooms: HashMap::new(),
+    };
+    let mut queue = VecDeque::new();
+
+    if explored.has_visited(map.entrance) {
+      layout.place(map.entrance, (0, 0, 0));
+      queue.push_back(map.entrance);
+    }
+
+    while let Some(room_id) = queue.pop_front() {
+      let pos = layout.positions[&room_id];
+      let mut neighbors: Vec<(RoomId, Position)> = Direction::iter()
+        .filter(|&dir| explored.has_seen_exit(room_id, dir))
+        .filter_map(|dir| {
+          map.room(room_id).get_exit(dir).map(|next| (next, step(pos, dir)))
+        }).collect();
+
+      // Lifts don't have any exits of their own, but as far as the
+      // player is concerned they go down to wherever they lead.
+      if let Some(destination) = map.teleport_destination(room_id) {
+        if explored.has_visited(destination) {
+          neighbors.push((destination, step(pos, Down)));
+        }
+      }
+
+      for (next, next_pos) in neighbors {
+        if layout.positions.contains_key(&next) ||
+           layout.rooms.contains_key(&next_pos) {
+          continue;
+        }
+        layout.place(next, next_pos);
+        if explored.has_visited(next) {
+          queue.push_back(next);
+        }
+      }
+    }
+
+    layout
+  }
+
+  fn place(&mut self, room_id: RoomId, pos: Position) {
+    self.positions.insert(room_id, pos);
+    self.rooms.insert(pos, room_id);
+  }
+
+  fn room_at(&self, pos: Position) -> Option<RoomId> {
+    self.rooms.get(&pos).cloned()
+  }
+
+  fn levels(&self) -> Vec<i32> {
+    let mut levels: Vec<i32> = self.positions.values()
+      .map(|&(_, _, z)| z).collect();
+    levels.sort();
+    levels.dedup();
+    levels.reverse();
+    levels
+  }
+}
+
+fn level_name(z: i32) -> String {
+  match z {
+    0 => String::from("Ground floor"),
+    1 => String::from("Upper floor"),
+    -1 => String::from("Basement"),
+    _ if z > 0 => format!("{} floors up", z),
+    _ => format!("{} floors down", -z),
+  }
+}
+
+// Returns true if the player knows that the rooms at the two positions
+// are connected, going from `a` in direction `d`.
+fn is_connected(map: &Map, explored: &Exploration, layout: &Layout,
+                a: Position, d: Direction) -> bool {
+  match (layout.room_at(a), layout.room_at(step(a, d))) {
+    (Some(from), Some(to)) => {
+      (explored.has_seen_exit(from, d) &&
+       map.room(from).get_exit(d) == Some(to)) ||
+      (explored.has_seen_exit(to, d.opposite()) &&
+       map.room(to).get_exit(d.opposite()) == Some(from))
+    },
+    _ => false,
+  }
+}
+
+// Draws the parts of the map that the player has explored, one level
+// at a time. Each room is drawn as a numbered box, with the current
+// room in angle brackets and rooms the player has seen a way into,
+// but hasn't yet visited, shown as "?". A legend below each level
+// gives the names of the numbered rooms.
+pub fn render(map: &Map, explored: &Exploration,
+              curr_room: RoomId) -> Vec<String> {
+  let layout = Layout::new(map, explored);
+  let mut number = 0;
+  let mut lines = Vec::new();
+
+  for z in layout.levels() {
+    let cells: Vec<Position> = layout.positions.values()
+      .filter(|&&(_, _, cz)| cz == z).cloned().collect();
+    let min_x = cells.iter().map(|&(x, _, _)| x).min().unwrap();
+    let max_x = cells.iter().map(|&(x, _, _)| x).max().unwrap();
+    let min_y = cells.iter().map(|&(_, y, _)| y).min().unwrap();
+    let max_y = cells.iter().map(|&(_, y, _)| y).max().unwrap();
+    let mut legend = Vec::new();
+
+    lines.push(format!("{}:", level_name(z)));
+    lines.push(String::new());
+
+    for y in min_y..(max_y + 1) {
+      let mut row = String::from("  ");
+      let mut below = String::from("  ");
+
+      for x in min_x..(max_x + 1) {
+        let pos = (x, y, z);
+        row.push_str(&match layout.room_at(pos) {
+          Some(room_id) if explored.has_visited(room_id) => {
+            number += 1;
+            legend.push(describe_legend_entry(map, explored, room_id,
+                                              number, curr_room));
+            if room_id == curr_room {
+              format!("<{:>2}>", number)
+            } else {
+              format!("[{:>2}]", number)
+            }
+          },
+          Some(_) => String::from("[ ?]"),
+          None => String::from("    "),
+        });
+        row.push(if is_connected(map, explored, &layout, pos, East) {
+          '-'
+        } else {
+          ' '
+        });
+        below.push_str(if is_connected(map, explored, &layout, pos, South) {
+          "  |  "
+        } else {
+          "     "
+        });
+      }
+
+      lines.push(String::from(row.trim_end()));
+      if y < max_y {
+        lines.push(String::from(below.trim_end()));
+      }
+    }
+
+    lines.push(String::new());
+    if !legend.is_empty() {
+      lines.extend(legend);
+      lines.push(String::new());
+    }
+  }
+
+  let elsewhere: Vec<&str> = explored.visited_rooms()
+    .filter(|room_id| !layout.positions.contains_key(room_id))
+    .map(|room_id| map.room(room_id).name.as_str())
+    .collect();
+  if !elsewhere.is_empty() {
+    lines.push(format!("You have also been to: {}.",
+                       elsewhere.join(", ")));
+    lines.push(String::new());
+  }
+
+  lines
+}
+
+fn describe_legend_entry(map: &Map, explored: &Exploration, room_id: RoomId,
+                         number: usize, curr_room: RoomId) -> String {
+  let mut entry = format!("  {:>2} {}", number, map.room(room_id).name);
+  let stairs: Vec<&str> = [Up, Down].iter()
+    .filter(|&&dir| explored.has_seen_exit(room_id, dir))
+    .map(|&dir| if dir == Up { "up" } else { "down" })
+    .collect();
+  if !stairs.is_empty() {
+    entry.push_str(&format!(" (stairs {})", stairs.join(" and ")));
+  }
+  if room_id == curr_room {
+    entry.push_str(" - you are here");
+  }
+  entry
+}
+
+impl GameState {
+  pub fn show_automap(&mut self) {
+    for line in render(&self.map, &self.explored, self.curr_room) {
+      wrapln!(self, "{}", line);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use map::Map;
+  use super::*;
+
+  fn render_castle(path: &[(&str, Option<Direction>)]) -> Vec<String> {
+    let map = Map::castle();
+    let mut explored = Exploration::new(map.num_rooms());
+    let mut room_id = map.entrance;
+    for &(key, dir) in path {
+      room_id = map.find_room(key).unwrap();
+      explored.visit(room_id);
+      if let Some(dir) = dir {
+        explored.see_exit(room_id, dir);
+      } else {
+        explored.see_all_exits(&map, room_id);
+      }
+    }
+    render(&map, &explored, room_id)
+  }
+
+  #[test]
+  fn test_render_explored_rooms() {
+    let lines = render_castle(&[
+      ("Entrance", None), ("Hallway", None), ("AudienceChamber", Some(South)),
+    ]);
+    assert_eq!(lines, vec![
+      "Ground floor:",
+      "",
+      "  [ 1]-[ 2]",
+      "         |",
+      "       < 3>",
+      "         |",
+      "       [ ?]",
+      "",
+      "   1 Entrance",
+      "   2 Hallway",
+      "   3 Audience Chamber - you are here",
+      "",
+    ]);
+  }
+
+  #[test]
+  fn test_render_groups_rooms_by_level() {
+    let lines = render_castle(&[
+      ("Entrance", None), ("Hallway", None), ("AudienceChamber", None),
+      ("GreatHall", Some(East)), ("InnerHallway", None),
+      ("UpperHallway", Some(Down)),
+    ]);
+    assert_eq!(&lines[..5], &[
+      "Upper floor:", "", "  < 1>", "",
+      "   1 Upper Hallway (stairs down) - you are here",
+    ]);
+    assert!(lines.contains(&String::from("Ground floor:")));
+    assert!(lines.contains(&String::from("   6 Inner Hallway \
+                                          (stairs up and down)")));
+    assert_eq!(&lines[lines.len() - 4..], &["Basement:", "", "  [ ?]", ""]);
+  }
+}
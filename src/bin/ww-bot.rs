@@ -0,0 +1,80 @@
+extern crate ww;
+
+use std::env;
+use std::process;
+
+use ww::bot;
+use ww::map::Map;
+use ww::map_options::MapOptions;
+use ww::command::ParserMode;
+
+const USAGE: &str = "Usage: ww-bot [--games <number>] [--seed <number>] \
+                     [--map <file> | --random-castle <seed> \
+                     [--rooms <number>] [--floors <number>]] \
+                     [--monsters <file>] [--items <file>] [--words]";
+
+const DEFAULT_GAMES: u64 = 1000;
+
+struct Options {
+  games: u64,
+  seed: u64,
+  map: Map,
+  parser: ParserMode,
+}
+
+fn parse_number(arg: &str, value: Option<String>) -> Result<u64, String> {
+  let value = value.unwrap_or_default();
+  value.parse::<u64>().map_err(|_| {
+    format!("Invalid {}: {:?}", &arg[2..], value)
+  })
+}
+
+impl Options {
+  fn from_args() -> Result<Self, String> {
+    let mut options = Self {
+      games: DEFAULT_GAMES,
+      seed: 0,
+      map: Map::castle(),
+      parser: ParserMode::Classic,
+    };
+    let mut map_options = MapOptions::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--games" => { options.games = parse_number(&arg, args.next())? },
+        "--seed" => { options.seed = parse_number(&arg, args.next())? },
+        "--words" => { options.parser = ParserMode::Words },
+        _ => {
+          if !map_options.parse_arg(&arg, &mut args)? {
+            return Err(format!("Unknown argument: {}", arg));
+          }
+        },
+      }
+    }
+
+    options.map = map_options.build()?;
+
+    Ok(options)
+  }
+}
+
+fn main() {
+  let options = match Options::from_args() {
+    Ok(options) => options,
+    Err(message) => {
+      eprintln!("{}", message);
+      eprintln!("{}", USAGE);
+      process::exit(1);
+    }
+  };
+
+  let seeds = options.seed..options.seed.saturating_add(options.games);
+  let report = bot::play_games(seeds, &options.map, options.parser);
+  for line in report.describe() {
+    println!("{}", line);
+  }
+  if !report.problems().is_empty() {
+    process::exit(1);
+  }
+}
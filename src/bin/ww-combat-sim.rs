@@ -0,0 +1,87 @@
+extern crate ww;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use ww::combat_sim;
+use ww::monsters::Bestiary;
+use ww::items::Catalogue;
+
+const USAGE: &str = "Usage: ww-combat-sim [--fights <number>] \
+                     [--seed <number>] [--monsters <file>] \
+                     [--items <file>]";
+
+const DEFAULT_FIGHTS: u32 = 10000;
+
+struct Options {
+  fights: u32,
+  seed: u64,
+  monsters: Bestiary,
+  items: Catalogue,
+}
+
+impl Options {
+  fn from_args() -> Result<Self, String> {
+    let mut options = Self {
+      fights: DEFAULT_FIGHTS,
+      seed: 0,
+      monsters: Bestiary::classic(),
+      items: Catalogue::classic(),
+    };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      let value = args.next().unwrap_or_default();
+      match arg.as_str() {
+        "--fights" => {
+          options.fights = value.parse().map_err(|_| {
+            format!("Invalid number of fights: {:?}", value)
+          })?;
+        },
+        "--seed" => {
+          options.seed = value.parse().map_err(|_| {
+            format!("Invalid seed: {:?}", value)
+          })?;
+        },
+        "--monsters" => {
+          let data = fs::read_to_string(&value).map_err(|e| {
+            format!("Unable to read {}: {}", value, e)
+          })?;
+          options.monsters = Bestiary::parse(&data).map_err(|e| {
+            format!("Invalid monsters {}: {}", value, e)
+          })?;
+        },
+        "--items" => {
+          let data = fs::read_to_string(&value).map_err(|e| {
+            format!("Unable to read {}: {}", value, e)
+          })?;
+          options.items = Catalogue::parse(&data).map_err(|e| {
+            format!("Invalid items {}: {}", value, e)
+          })?;
+        },
+        _ => { return Err(format!("Unknown argument: {}", arg)); }
+      }
+    }
+
+    Ok(options)
+  }
+}
+
+fn main() {
+  let options = match Options::from_args() {
+    Ok(options) => options,
+    Err(message) => {
+      eprintln!("{}", message);
+      eprintln!("{}", USAGE);
+      process::exit(1);
+    }
+  };
+
+  let results = combat_sim::simulate_all(&options.monsters, &options.items,
+                                         options.fights, options.seed);
+  for line in combat_sim::describe(&options.monsters, &options.items,
+                                   &results) {
+    println!("{}", line);
+  }
+}
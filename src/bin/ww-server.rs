@@ -0,0 +1,186 @@
+extern crate ww;
+
+use std::env;
+use std::io::{self, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use ww::platform::{AbstractPlatform, ProcessPlatform};
+use ww::platform::stdio::StdioPlatform;
+use ww::game_state::GameState;
+use ww::map::Map;
+use ww::map_options::MapOptions;
+use ww::command::ParserMode;
+
+const USAGE: &str = "Usage: ww-server [--listen <address>] \
+                     [--map <file> | --random-castle <seed> \
+                     [--rooms <number>] [--floors <number>]] \
+                     [--monsters <file>] [--items <file>] [--words]";
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:2323";
+
+// How many games can be played at once. Anyone who connects while the
+// server is full is turned away.
+const MAX_GAMES: usize = 32;
+
+// How long a player can go without typing anything (or without reading
+// what the game writes) before they're disconnected, so that abandoned
+// connections don't hold on to a game forever.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+struct Options {
+  address: String,
+  map: Map,
+  parser: ParserMode,
+}
+
+impl Options {
+  fn from_args() -> Result<Self, String> {
+    let mut options = Self {
+      address: String::from(DEFAULT_ADDRESS),
+      map: Map::castle(),
+      parser: ParserMode::Classic,
+    };
+    let mut map_options = MapOptions::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--listen" => {
+          options.address = args.next().ok_or("--listen requires an \
+                                               address")?;
+        },
+        "--words" => { options.parser = ParserMode::Words },
+        _ => {
+          if !map_options.parse_arg(&arg, &mut args)? {
+            return Err(format!("Unknown argument: {}", arg));
+          }
+        },
+      }
+    }
+
+    options.map = map_options.build()?;
+
+    Ok(options)
+  }
+}
+
+// Telnet clients expect lines to end with CR LF, while the game only
+// ever writes LF.
+struct TelnetWriter<W: Write> {
+  inner: W,
+}
+
+impl<W: Write> Write for TelnetWriter<W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    for (i, line) in buf.split(|&c| c == b'\n').enumerate() {
+      if i > 0 {
+        self.inner.write_all(b"\r\n")?;
+      }
+      self.inner.write_all(line)?;
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+// Counts a game as being played for as long as it's alive, even if the
+// game panics.
+struct GameSlot {
+  games: Arc<AtomicUsize>,
+}
+
+impl GameSlot {
+  fn claim(games: &Arc<AtomicUsize>) -> Option<Self> {
+    let claimed = games.fetch_update(Ordering::SeqCst, Ordering::SeqCst,
+                                     |count| {
+      if count < MAX_GAMES { Some(count + 1) } else { None }
+    });
+    claimed.ok().map(|_| Self { games: games.clone() })
+  }
+}
+
+impl Drop for GameSlot {
+  fn drop(&mut self) {
+    self.games.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+fn play(stream: TcpStream, map: Map, parser: ParserMode) -> io::Result<()> {
+  stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+  stream.set_write_timeout(Some(IDLE_TIMEOUT))?;
+  let input = BufReader::new(stream.try_clone()?);
+  let platform = StdioPlatform::connect(input, TelnetWriter { inner: stream });
+
+  let mut state = GameState::new(StdioPlatform::random_seed(), map,
+                                 Box::new(platform));
+  state.parser = parser;
+  state.map.populate(&mut state.rng);
+
+  state.platform.clear_screen();
+  while !state.is_finished() && !state.platform.is_disconnected() {
+    state.tick();
+  }
+  Ok(())
+}
+
+fn main() {
+  let options = match Options::from_args() {
+    Ok(options) => options,
+    Err(message) => {
+      eprintln!("{}", message);
+      eprintln!("{}", USAGE);
+      process::exit(1);
+    }
+  };
+
+  let listener = match TcpListener::bind(&options.address) {
+    Ok(listener) => listener,
+    Err(e) => {
+      eprintln!("Unable to listen on {}: {}", options.address, e);
+      process::exit(1);
+    }
+  };
+  println!("Listening on {}.", options.address);
+
+  // Every connection gets its own thread, and with it its own game.
+  let games = Arc::new(AtomicUsize::new(0));
+  for stream in listener.incoming() {
+    let mut stream = match stream {
+      Ok(stream) => stream,
+      Err(e) => {
+        eprintln!("Unable to accept connection: {}", e);
+        continue;
+      }
+    };
+    let peer = stream.peer_addr().map(|addr| addr.to_string())
+      .unwrap_or_else(|_| String::from("unknown"));
+    let slot = match GameSlot::claim(&games) {
+      Some(slot) => slot,
+      None => {
+        println!("{} turned away, since the server is full.", peer);
+        let _ = stream.write_all(b"Sorry, too many people are playing \
+                                   right now. Please try again later.\r\n");
+        continue;
+      }
+    };
+    let map = options.map.clone();
+    let parser = options.parser;
+
+    println!("{} connected.", peer);
+    thread::spawn(move || {
+      let _slot = slot;
+      if let Err(e) = play(stream, map, parser) {
+        eprintln!("Error playing with {}: {}", peer, e);
+      }
+      println!("{} disconnected.", peer);
+    });
+  }
+}
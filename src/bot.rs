@@ -0,0 +1,463 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use map::{Map, RoomId, RoomContents};
+use direction::Direction;
+use items::{Item, ItemId};
+use inventory::SHOP_KEYS;
+use combat::FLEE_PROMPT;
+use sized_enum::SizedEnum;
+use command::ParserMode;
+use events::GameEvent;
+use high_scores::Outcome;
+use game_state::{GameState, GameMode};
+use platform::ProcessPlatform;
+use platform::scripted::ScriptedPlatform;
+
+const BOT_NAME: &str = "Robot";
+
+// If a game needs more input than this, the bot is assumed to be stuck.
+const MAX_INPUTS_PER_GAME: usize = 2000;
+
+// The bot eats once its strength gets this low.
+const HUNGRY_STRENGTH: i32 = 30;
+
+// The bot heads for the exit once it can't afford to wander around
+// for much longer.
+const STRENGTH_MARGIN: i32 = 20;
+
+// The bot runs away from monsters at least this dangerous.
+const SCARY_DANGER_LEVEL: i32 = 11;
+
+const MAX_FOOD: i32 = 15;
+
+// Estimated strength lost per move.
+const STRENGTH_PER_MOVE: i32 = 5;
+
+// Plays the game by looking at the game's state and choosing what to
+// type, just like a (very methodical) player would.
+struct Bot {
+  // How many units of food the bot asked to buy, when the shop asks
+  // how many it wants.
+  food_to_buy: i32,
+
+  // The rooms the bot has already searched for secret passages.
+  searched: Vec<RoomId>,
+}
+
+impl Bot {
+  fn new() -> Self {
+    Self { food_to_buy: 0, searched: Vec::new() }
+  }
+
+  fn next_input(&mut self, state: &GameState) -> String {
+    let prompt = state.prompt();
+    match state.curr_mode {
+      GameMode::AskName => {
+        // We never have a saved game to resume, but just in case.
+        if prompt.contains("resume") { "n" } else { BOT_NAME }.into()
+      },
+      GameMode::Primary => self.choose_action(state),
+      GameMode::Inventory if prompt.contains("How many") => {
+        self.food_to_buy.to_string()
+      },
+      GameMode::Inventory => self.choose_purchase(state),
+      GameMode::EatFood => {
+        let wanted = (HUNGRY_STRENGTH * 2 - state.strength) /
+          strength_per_food(state).max(1) + 1;
+        wanted.max(1).min(state.food()).to_string()
+      },
+      GameMode::Combat(_) if prompt == FLEE_PROMPT => {
+        self.choose_flight(state)
+      },
+      GameMode::Combat(combat) => {
+        if !prompt.contains("run away") {
+          String::new()
+        } else if combat.danger_level() >= SCARY_DANGER_LEVEL {
+          "y".into()
+        } else {
+          "n".into()
+        }
+      },
+      _ => "q".into(),
+    }
+  }
+
+  fn next_purchase(&self, state: &GameState) -> Option<ItemId> {
+    let catalogue = &state.map.catalogue;
+    shopping_list(&state.map).into_iter().find(|&id| {
+      !state.items.owns(id) && catalogue.get(id).price <= state.wealth
+    }).or_else(|| {
+      catalogue.food().filter(|&id| {
+        state.food() < MAX_FOOD && catalogue.get(id).price <= state.wealth
+      })
+    })
+  }
+
+  fn choose_purchase(&mut self, state: &GameState) -> String {
+    match self.next_purchase(state) {
+      Some(id) if Some(id) == state.map.catalogue.food() => {
+        let affordable = state.wealth / state.map.catalogue.get(id).price;
+        self.food_to_buy = affordable.min(MAX_FOOD - state.food());
+        key_for_item(id)
+      },
+      Some(id) => key_for_item(id),
+      None => "0".into(),
+    }
+  }
+
+  fn choose_action(&mut self, state: &GameState) -> String {
+    let here = state.curr_room;
+    let food = state.food();
+
+    if let Some(RoomContents::Treasure(_)) = state.map.room(here).contents {
+      if state.can_player_see() {
+        return "p".into();
+      }
+    }
+    if state.strength <= HUNGRY_STRENGTH && food > 0 {
+      return "c".into();
+    }
+    if self.next_purchase(state).is_some() {
+      return "i".into();
+    }
+
+    // Explore until we're too weak to risk it, then leave.
+    let to_exit = path_to(state, here, |r| state.map.is_exit(r));
+    let reserves = state.strength + food * strength_per_food(state);
+    let must_leave = to_exit.is_some_and(|(_, distance)| {
+      reserves - distance * STRENGTH_PER_MOVE <= STRENGTH_MARGIN
+    });
+    let unexplored = path_to(state, here, |r| {
+      !state.map.is_exit(r) && !state.explored.has_visited(r)
+    });
+    let next = if must_leave { to_exit } else { unexplored.or(to_exit) };
+    match next.or_else(|| self.path_to_search(state)) {
+      Some((dir, _)) if state.is_exit_locked(dir) => "k".into(),
+      Some((dir, _)) => dir.character().to_string(),
+      None if self.should_search_here(state) => {
+        self.searched.push(here);
+        "x".into()
+      },
+      None => "q".into(),
+    }
+  }
+
+  // Once there's nowhere left to go, the bot looks for secret passages
+  // in every room it's been to, as long as it has a light to see them
+  // by.
+  fn should_search_here(&self, state: &GameState) -> bool {
+    state.can_player_see() && !self.searched.contains(&state.curr_room)
+  }
+
+  fn path_to_search(&self, state: &GameState) -> Option<(Direction, i32)> {
+    if !carries_light(state) || self.should_search_here(state) {
+      return None;
+    }
+    path_to(state, state.curr_room, |r| {
+      state.explored.has_visited(r) && !state.map.is_exit(r) &&
+        !self.searched.contains(&r)
+    })
+  }
+
+  // Picks any way out of the room, preferring ones that don't lead
+  // straight back into danger.
+  fn choose_flight(&self, state: &GameState) -> String {
+    let room = state.map.room(state.curr_room);
+    let exits: Vec<Direction> = Direction::iter().filter(|&dir| {
+      room.get_open_exit(dir).is_some()
+    }).collect();
+    let safe = exits.iter().cloned().find(|&dir| {
+      let next = room.get_open_exit(dir).unwrap();
+      state.map.room(next).contents.is_none()
+    });
+    match safe.or_else(|| exits.first().cloned()) {
+      Some(dir) => dir.character().to_string(),
+      None => "n".into(),
+    }
+  }
+}
+
+// Equipment is bought in this order, as money allows: the cheapest way
+// to see in the dark, then the keys to any locked doors, then whatever
+// makes fights the least dangerous.
+fn shopping_list(map: &Map) -> Vec<ItemId> {
+  let catalogue = &map.catalogue;
+  let wanted: Vec<ItemId> = catalogue.ids().filter(|&id| {
+    !catalogue.get(id).stackable
+  }).collect();
+  let light = wanted.iter().cloned().filter(|&id| {
+    catalogue.get(id).light
+  }).min_by_key(|&id| catalogue.get(id).price);
+  let mut keys: Vec<ItemId> = Vec::new();
+  for room_id in map.room_ids() {
+    for dir in Direction::iter() {
+      let lock = map.room(room_id).exit(dir).and_then(|exit| {
+        exit.lock.as_ref()
+      });
+      if let Some(id) = lock.and_then(|key| catalogue.find(key)) {
+        if !keys.contains(&id) {
+          keys.push(id);
+        }
+      }
+    }
+  }
+  let mut gear: Vec<ItemId> = wanted.iter().cloned().filter(|&id| {
+    catalogue.get(id).affects_combat() && !keys.contains(&id)
+  }).collect();
+  gear.sort_by(|&a, &b| {
+    protection(catalogue.get(a)).total_cmp(&protection(catalogue.get(b)))
+  });
+  light.into_iter().chain(keys).chain(gear).collect()
+}
+
+fn carries_light(state: &GameState) -> bool {
+  state.items.owned().any(|id| state.map.catalogue.get(id).light)
+}
+
+// Where the exit in the given direction leads, if the bot can go
+// through it, perhaps after unlocking it with a key it's carrying.
+fn usable_exit(state: &GameState, room_id: RoomId,
+               dir: Direction) -> Option<RoomId> {
+  state.map.room(room_id).exit(dir).filter(|exit| {
+    let has_key = exit.lock.as_ref().and_then(|key| {
+      state.map.catalogue.find(key)
+    }).is_some_and(|id| state.items.owns(id));
+    !exit.hidden && (!exit.locked || has_key)
+  }).map(|exit| exit.to)
+}
+
+// How much an item scales the danger level of a fight; lower is better.
+fn protection(item: &Item) -> f32 {
+  item.weapon.iter().chain(item.armor.iter()).map(|modifier| {
+    modifier.num as f32 / modifier.den as f32
+  }).fold(1.0, f32::min)
+}
+
+fn strength_per_food(state: &GameState) -> i32 {
+  state.map.catalogue.food().map_or(0, |id| {
+    state.map.catalogue.get(id).nourishment()
+  })
+}
+
+fn key_for_item(id: ItemId) -> String {
+  SHOP_KEYS.chars().nth(id.0).map_or(String::from("0"), String::from)
+}
+
+// Returns the first step of the shortest path to a room that matches
+// the given predicate, along with how many moves it takes. Lifts and
+// doors the bot can unlock are taken into account, and the path never
+// goes through the exit unless that's where it's headed.
+fn path_to<F>(state: &GameState, from: RoomId,
+              is_goal: F) -> Option<(Direction, i32)>
+  where F: Fn(RoomId) -> bool
+{
+  let map = &state.map;
+  let mut first_steps: Vec<Option<(Direction, i32)>> =
+    vec![None; map.num_rooms()];
+  let mut queue = VecDeque::new();
+  let mut seen = vec![false; map.num_rooms()];
+  seen[from.0] = true;
+  queue.push_back(from);
+
+  while let Some(room_id) = queue.pop_front() {
+    if room_id != from && is_goal(room_id) {
+      return first_steps[room_id.0];
+    }
+    if map.is_exit(room_id) {
+      continue;
+    }
+    for dir in Direction::iter() {
+      if let Some(next) = usable_exit(state, room_id, dir) {
+        let next = map.teleport_destination(next).unwrap_or(next);
+        if !seen[next.0] {
+          seen[next.0] = true;
+          first_steps[next.0] = Some(match first_steps[room_id.0] {
+            Some((first, distance)) => (first, distance + 1),
+            None => (dir, 1),
+          });
+          queue.push_back(next);
+        }
+      }
+    }
+  }
+  None
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BotGame {
+  pub seed: u64,
+  pub score: i32,
+  pub outcome: Option<Outcome>,
+
+  // The names of every mode the game was in at some point.
+  pub modes: Vec<&'static str>,
+
+  // Set if the game panicked, or the bot had to give up because the
+  // game never finished.
+  pub problem: Option<String>,
+}
+
+// Plays a single game to completion with the given seed.
+pub fn play_game(seed: u64, map: &Map, parser: ParserMode) -> BotGame {
+  let script = ScriptedPlatform::new();
+  let mut state = GameState::new(seed, map.clone(),
+                                 Box::new(script.clone()));
+  state.parser = parser;
+  state.map.populate(&mut state.rng);
+
+  let outcome = Rc::new(RefCell::new(None));
+  let observed_outcome = outcome.clone();
+  state.add_observer(move |event| {
+    match *event {
+      GameEvent::Escaped => {
+        *observed_outcome.borrow_mut() = Some(Outcome::Escaped)
+      },
+      GameEvent::Died => {
+        *observed_outcome.borrow_mut() = Some(Outcome::Died)
+      },
+      _ => {},
+    }
+  });
+
+  let mut bot = Bot::new();
+  let mut modes = Vec::new();
+  let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    for _ in 0..MAX_INPUTS_PER_GAME {
+      script.run_until_blocked(&mut state);
+      script.take_transcript();
+      let mode = state.curr_mode.name();
+      if !modes.contains(&mode) {
+        modes.push(mode);
+      }
+      if state.is_finished() {
+        return Ok(state.get_score_breakdown(*outcome.borrow()).total());
+      }
+      script.push_input(bot.next_input(&state));
+    }
+    Err(String::from("The game never finished."))
+  }));
+
+  let (score, problem) = match result {
+    Ok(Ok(score)) => (score, None),
+    Ok(Err(message)) => (0, Some(message)),
+    Err(_) => (0, Some(String::from("The game panicked."))),
+  };
+  let outcome = if problem.is_some() {
+    None
+  } else {
+    Some(outcome.borrow().unwrap_or(Outcome::Quit))
+  };
+  BotGame { seed, score, outcome, modes, problem }
+}
+
+#[derive(Debug, Default)]
+pub struct BotReport {
+  pub games: Vec<BotGame>,
+}
+
+impl BotReport {
+  pub fn count(&self, outcome: Outcome) -> usize {
+    self.games.iter().filter(|game| game.outcome == Some(outcome)).count()
+  }
+
+  pub fn has_seen_mode(&self, mode: &str) -> bool {
+    self.games.iter().any(|game| game.modes.contains(&mode))
+  }
+
+  pub fn problems(&self) -> Vec<&BotGame> {
+    self.games.iter().filter(|game| game.problem.is_some()).collect()
+  }
+
+  // Returns a human-readable summary of the games.
+  pub fn describe(&self) -> Vec<String> {
+    let total = self.games.len();
+    let percent = |n: usize| {
+      if total == 0 { 0.0 } else { 100.0 * n as f32 / total as f32 }
+    };
+    let mut scores: Vec<i32> = self.games.iter().map(|game| {
+      game.score
+    }).collect();
+    scores.sort();
+
+    let mut lines = vec![format!("Played {} games.", total)];
+    for &outcome in [Outcome::Escaped, Outcome::Died, Outcome::Quit].iter() {
+      let n = self.count(outcome);
+      lines.push(format!("  {:<8} {:>6} ({:.1}%)", outcome, n, percent(n)));
+    }
+    if !scores.is_empty() {
+      let mean = scores.iter().sum::<i32>() as f32 / scores.len() as f32;
+      lines.push(format!("Scores: min {}, median {}, mean {:.1}, max {}",
+                         scores[0], scores[scores.len() / 2], mean,
+                         scores[scores.len() - 1]));
+    }
+    for game in self.problems() {
+      lines.push(format!("Seed {}: {}", game.seed,
+                         game.problem.as_ref().unwrap()));
+    }
+    lines
+  }
+}
+
+// Plays one game for each of the given seeds.
+pub fn play_games<I>(seeds: I, map: &Map, parser: ParserMode) -> BotReport
+  where I: Iterator<Item = u64>
+{
+  BotReport {
+    games: seeds.map(|seed| play_game(seed, map, parser)).collect(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_path_finding() {
+    let (mut state, _) = new_empty_game();
+    let entrance = state.map.entrance;
+    let hallway = state.map.find_room("Hallway").unwrap();
+    assert_eq!(path_to(&state, entrance, |r| r == hallway),
+               Some((Direction::East, 1)));
+    assert!(path_to(&state, entrance, |r| state.map.is_exit(r)).is_some());
+
+    state.map.lock_exit(entrance, Direction::East, "sword");
+    assert_eq!(path_to(&state, entrance, |r| r == hallway), None);
+    give_item(&mut state, "sword", 1);
+    assert_eq!(path_to(&state, entrance, |r| r == hallway),
+               Some((Direction::East, 1)));
+  }
+
+  #[test]
+  fn test_bot_plays_to_completion() {
+    for &parser in [ParserMode::Classic, ParserMode::Words].iter() {
+      let report = play_games(0..40, &Map::castle(), parser);
+      assert_eq!(report.problems(), Vec::<&BotGame>::new());
+      assert!(report.count(Outcome::Escaped) > 0);
+      for mode in &["ask_name", "primary", "inventory", "eat_food",
+                    "combat", "finished"] {
+        assert!(report.has_seen_mode(mode), "never saw {}", mode);
+      }
+    }
+  }
+
+  // The only ways to the exit are through secret passages, and the exit
+  // itself is locked.
+  #[test]
+  fn test_bot_unlocks_doors_and_searches() {
+    let mut map = Map::castle();
+    let vestibule = map.find_room("RearVestibule").unwrap();
+    let kitchen = map.find_room("Kitchen").unwrap();
+    let small_room = map.find_room("SmallRoom").unwrap();
+    map.lock_exit(vestibule, Direction::East, "sword");
+    map.hide_exit(kitchen, Direction::South);
+    map.hide_exit(small_room, Direction::North);
+
+    let report = play_games(0..20, &map, ParserMode::Classic);
+    assert_eq!(report.problems(), Vec::<&BotGame>::new());
+    assert!(report.count(Outcome::Escaped) > 0);
+  }
+}
@@ -1,30 +1,16 @@
-use monsters::MonsterId;
 use map::RoomContents;
 use game_state::{GameState, GameMode};
-use command::CommandInfo;
-use items::Item::*;
+use command::{CommandInfo, ParsedCommand};
+use combat_engine::{CombatState, CombatPhase, CombatEvent, CombatStep,
+                    Equipment};
+use util;
 use direction::Direction;
 use sized_enum::SizedEnum;
-use platform::*;
+use events::GameEvent;
 
 use self::FleeCommand::*;
-use self::CombatPhase::*;
 
-const CHANCE_TO_RUN: f32 = 0.3;
-
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub enum CombatPhase {
-  Preparation,
-  Battle,
-  Aftermath,
-}
-
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub struct CombatState {
-  phase: CombatPhase,
-  enemy: MonsterId,
-  ff: i32,
-}
+pub const FLEE_PROMPT: &str = "What direction will you run? ";
 
 #[derive(Debug, Copy, Clone)]
 pub enum FleeCommand {
@@ -34,84 +20,152 @@ pub enum FleeCommand {
 command_processor!(FleeCommand, {
   Direction::iter().map(|dir| {
     CommandInfo::new(dir.character(), format!("flee {}", dir), Flee(dir))
+      .phrases(&[format!("flee {}", dir), format!("go {}", dir),
+                 dir.to_string()])
   }).collect()
 });
 
+// The rules of combat live in the combat engine; this is just the part
+// that talks to the player and shows them what happened.
 impl GameState {
   pub fn maybe_start_combat(&mut self) -> bool {
     if let Some(RoomContents::Terror(monster_id)) =
         self.map.room(self.curr_room).contents {
-      wrapln!("\nDanger... There is a monster here....");
-      Self::pause();
-      wrapln!("\nIt is a {}!", monster_id);
-      let ff = self.get_modified_ff(monster_id.ferocity_factor());
-      wrapln!("\nThe danger level is {}!!\n", ff);
-      Self::pause();
-      self.set_mode(GameMode::Combat(CombatState {
-        phase: Preparation,
-        enemy: monster_id,
-        ff: ff,
-      }));
+      let monster = self.map.monsters.get(monster_id).clone();
+      let equipment = Equipment::from_inventory(&self.items,
+                                                &self.map.catalogue);
+      let state = CombatState::new(monster_id, &monster, &equipment);
+      wrapln!(self, "\nDanger... There is a monster here....");
+      self.pause();
+      wrapln!(self, "\nIt is {}!", monster.with_article());
+      if !monster.description.is_empty() {
+        wrapln!(self, "{}", monster.description);
+      }
+      wrapln!(self, "\nThe danger level is {}!!\n", state.danger_level());
+      self.pause();
+      self.emit(GameEvent::CombatStarted(monster_id));
+      self.set_mode(GameMode::Combat(state));
       return true;
     }
     false
   }
 
-  fn get_modified_ff(&self, base_ff: i32) -> i32 {
-    let mut ff = base_ff;
-    let axe = self.items.owns(Axe);
-    let sword = self.items.owns(Sword);
-
-    if self.items.owns(Armor) { ff = 3 * (ff / 4); }
-    if !axe && !sword {
-      ff + (ff / 5)
-    } else if axe && !sword {
-      4 * (ff / 5)
-    } else if sword && !axe {
-      3 * (ff / 4)
-    } else {
-      3 * (ff / 5)
+  fn prepare(&mut self) {
+    let owned: Vec<_> = self.items.owned().map(|id| {
+      self.map.catalogue.get(id).clone()
+    }).collect();
+    for item in owned.iter().filter(|item| item.armor.is_some()) {
+      wrapln!(self, "Your {} increases your chance of success.", item.noun);
     }
+
+    let weapons: Vec<_> = owned.iter().filter(|item| {
+      item.weapon.is_some()
+    }).collect();
+    match weapons.len() {
+      0 => {
+        wrapln!(self, "You have no weapons.");
+        wrapln!(self, "You must fight with bare hands.");
+      },
+      1 => { wrapln!(self, "You must fight with your {}.", weapons[0].noun) },
+      _ => {
+        let names = weapons.iter().map(|item| item.name.as_str()).collect();
+        wrapln!(self, "You are dual-wielding {} like a boss.",
+                util::friendly_join(names));
+      },
+    }
+    self.pause();
   }
 
-  fn prepare(&self) {
-    if self.items.owns(Armor) {
-      wrapln!("Your armor increases your chance of success.");
+  fn narrate_combat_event(&mut self, state: &CombatState,
+                          event: CombatEvent) {
+    let enemy = state.enemy();
+    let name = self.map.monsters.get(enemy).name.clone();
+    match event {
+      CombatEvent::Fled => {},
+      CombatEvent::FailedToFlee => {
+        wrapln!(self, "Your craven attempt to escape has failed.");
+      },
+      CombatEvent::MonsterAttacks => {
+        wrapln!(self, "The {} attacks!", name);
+        self.pause();
+      },
+      CombatEvent::PlayerAttacks => {
+        wrapln!(self, "You attack!");
+        self.pause();
+      },
+      CombatEvent::MonsterWounded => {
+        wrapln!(self, "\nYou manage to wound it!");
+        self.pause();
+      },
+      CombatEvent::PlayerWounded => {
+        wrapln!(self, "\nThe monster wounds you!");
+        self.pause();
+      },
+      CombatEvent::KillingBlow => {
+        wrapln!(self, "\nThe monster lands a killing blow!");
+        self.pause();
+      },
+      CombatEvent::Won => {
+        wrapln!(self, "\nWounded and ashamed, the {} scurries off.", name);
+        wrapln!(self, "\nYou are victorious!");
+        self.monsters_killed += 1;
+        self.emit(GameEvent::CombatWon(enemy));
+      },
+      CombatEvent::DroppedLoot(_) => {
+        wrapln!(self, "\nIt leaves some treasure behind.");
+      },
+      CombatEvent::Lost => {
+        wrapln!(self, "\nThe {} knocks you down!", name);
+        wrapln!(self, "\nYou are at its mercy!");
+        self.pause();
+        wrapln!(self, "\nIt appears to be in a generous mood and wanders off.");
+        self.emit(GameEvent::CombatLost(enemy));
+      },
     }
+  }
 
-    let axe = self.items.owns(Axe);
-    let sword = self.items.owns(Sword);
-
-    if !axe && !sword {
-      wrapln!("You have no weapons.");
-      wrapln!("You must fight with bare hands.");
-    } else if axe && !sword {
-      wrapln!("You have only an axe to fight with.");
-    } else if sword && !axe {
-      wrapln!("You must fight with your sword.");
-    } else {
-      wrapln!("You are dual-wielding a sword and axe like a boss.");
+  // Shows what happened during a step of the fight and updates the game
+  // to match.
+  fn apply_combat_step(&mut self, state: &CombatState, step: CombatStep) {
+    self.strength = step.strength;
+    let mut loot = None;
+    for &event in step.events.iter() {
+      if let CombatEvent::DroppedLoot(amount) = event {
+        loot = Some(RoomContents::Treasure(amount));
+      }
+      self.narrate_combat_event(state, event);
+    }
+    match step.state {
+      Some(next) => { self.curr_mode = GameMode::Combat(next) },
+      None => {
+        wrapln!(self, "\n");
+        self.pause();
+        self.map.mut_room(self.curr_room).contents = loot;
+        self.set_mode(GameMode::Primary);
+      },
     }
-    Self::pause();
   }
 
   fn press_enter_to_fight(&mut self, state: CombatState) {
     self.ask("Press enter to fight! ", move |game_state, _| {
       game_state.prepare();
-      wrapln!("\n");
-      game_state.curr_mode = GameMode::Combat(CombatState {
-        phase: Battle,
-        .. state
-      });
+      wrapln!(game_state, "\n");
+      let step = state.fight(game_state.strength, &mut game_state.rng);
+      game_state.apply_combat_step(&state, step);
     });
   }
 
-  fn ask_direction_to_run(&mut self) {
-    self.ask("What direction will you run? ", move |game_state, input| {
-      if let Some(Flee(dir)) = FleeCommand::get_from_input(input) {
+  // The player stays in combat until they've actually got away, so that
+  // anyone watching can tell they're fleeing.
+  fn ask_direction_to_run(&mut self, state: CombatState) {
+    self.ask(FLEE_PROMPT, move |game_state, input| {
+      let parsed = FleeCommand::get_from_input(game_state, input);
+      if let Some(ParsedCommand { cmd: Flee(dir), .. }) = parsed {
+        game_state.set_mode(GameMode::Primary);
         if game_state.try_to_move(dir) {
           return;
         }
+        game_state.set_mode(GameMode::Combat(state));
       }
       game_state.ask_again();
     });
@@ -122,16 +176,20 @@ impl GameState {
     self.ask("Will you run away like a coward? ", move |game_state, input| {
       let lower_input = input.to_lowercase();
       if lower_input.starts_with('y') {
-        if Platform::random() <= CHANCE_TO_RUN {
-          game_state.set_mode(GameMode::Primary);
-          game_state.ask_direction_to_run();
+        let step = state.run_away(game_state.strength, &mut game_state.rng);
+        let fled = step.state.is_none();
+        for event in step.events {
+          game_state.narrate_combat_event(&state, event);
+        }
+        if fled {
+          game_state.emit(GameEvent::Fled(state.enemy()));
+          game_state.ask_direction_to_run(state);
           return;
         }
-        wrapln!("Your craven attempt to escape has failed.");
       } else if lower_input.starts_with('n') {
-        wrapln!("Awesome.");
+        wrapln!(game_state, "Awesome.");
       } else {
-        wrapln!("Please answer 'yes' or 'no'.");
+        wrapln!(game_state, "Please answer 'yes' or 'no'.");
         game_state.ask_again();
         return;
       }
@@ -139,64 +197,106 @@ impl GameState {
     });
   }
 
-  fn tick_battle_phase(&mut self, state: &CombatState) {
-    let mut ff = state.ff;
-    let mut phase = state.phase;
-
-    if Platform::random() > 0.5 {
-      wrapln!("The {} attacks!", state.enemy);
-    } else {
-      wrapln!("You attack!");
-    }
-    Self::pause();
-    if Platform::random() > 0.5 {
-      wrapln!("\nYou manage to wound it!");
-      ff = 5 * ff / 6;
-      Self::pause();
-    }
-    if Platform::random() > 0.5 {
-      self.strength -= 5;
-      if self.strength > 0 {
-        wrapln!("\nThe monster wounds you!");
-      } else {
-        wrapln!("\nThe monster lands a killing blow!");
-      }
-      Self::pause();
-    }
-    if Platform::random() <= 0.35 {
-      phase = Aftermath;
+  pub fn tick_combat_mode(&mut self, state: &CombatState) {
+    match state.phase() {
+      CombatPhase::Preparation => { self.tick_preparation_phase(state) },
+      _ => {
+        let step = state.fight(self.strength, &mut self.rng);
+        self.apply_combat_step(state, step);
+      },
     }
-    self.curr_mode = GameMode::Combat(CombatState {
-      phase: phase,
-      ff: ff,
-      .. *state
-    });
   }
+}
 
-  fn tick_aftermath_phase(&mut self, state: &CombatState) {
-    if Platform::random_i32(0, 16) > state.ff {
-      wrapln!("\nWounded and ashamed, the {} scurries off.",
-              state.enemy);
-      wrapln!("\nYou are victorious!");
-      self.monsters_killed += 1;
-    } else {
-      wrapln!("\nThe {} knocks you down!", state.enemy);
-      wrapln!("\nYou are at its mercy!");
-      Self::pause();
-      wrapln!("\nIt appears to be in a generous mood and wanders off.");
-      self.strength /= 2;
-    }
-    wrapln!("\n");
-    Self::pause();
-    self.map.mut_room(self.curr_room).contents = None;
-    self.set_mode(GameMode::Primary);
+#[cfg(test)]
+mod tests {
+  use map::RoomContents::{Terror, Treasure};
+  use game_state::{GameState, GameMode};
+  use platform::scripted::*;
+  use super::FLEE_PROMPT;
+
+  fn enter_werewolf_lair() -> (GameState, ScriptedPlatform) {
+    let (mut state, script) = new_empty_game();
+    give_item(&mut state, "torch", 1);
+    let hallway = state.map.find_room("Hallway").unwrap();
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.mut_room(hallway).contents = Some(Terror(werewolf));
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+    (state, script)
   }
 
-  pub fn tick_combat_mode(&mut self, state: &CombatState) {
-    match state.phase {
-      Preparation => { self.tick_preparation_phase(state) },
-      Battle => { self.tick_battle_phase(state) },
-      Aftermath => { self.tick_aftermath_phase(state) },
-    }
+  #[test]
+  fn test_winning_a_fight() {
+    let (mut state, script) = enter_werewolf_lair();
+    assert!(script.take_output()
+      .contains("It is a ferocious werewolf!"));
+
+    // You attack, nobody is wounded, the battle ends, and the
+    // werewolf loses.
+    state.rng.queue(&[0.0, 0.0, 0.0, 0.0, 0.99]);
+    script.push_inputs(&["n", ""]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("You must fight with bare hands."));
+    assert!(output.contains("You are victorious!"));
+    assert_eq!(state.monsters_killed, 1);
+    assert_eq!(state.strength, 95);
+    assert_eq!(state.curr_mode, GameMode::Primary);
+    assert!(state.map.room(state.curr_room).contents.is_none());
+  }
+
+  #[test]
+  fn test_defeated_monsters_leave_loot() {
+    let (mut state, script) = new_empty_game();
+    give_item(&mut state, "torch", 1);
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.monsters.get_mut(werewolf).loot = (20, 30);
+    let hallway = state.map.find_room("Hallway").unwrap();
+    state.map.mut_room(hallway).contents = Some(Terror(werewolf));
+    state.rng.queue(&[0.0, 0.0, 0.0, 0.0, 0.99, 0.0]);
+    script.push_inputs(&["Alice", "e", "n", ""]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("Its fur bristles"));
+    assert!(output.contains("It leaves some treasure behind."));
+    assert_eq!(state.map.room(hallway).contents, Some(Treasure(20)));
+  }
+
+  #[test]
+  fn test_losing_a_fight() {
+    let (mut state, script) = enter_werewolf_lair();
+
+    // The werewolf attacks and wounds you, then wins.
+    state.rng.queue(&[0.99, 0.0, 0.99, 0.0, 0.0]);
+    script.push_inputs(&["n", ""]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("The monster wounds you!"));
+    assert!(output.contains("You are at its mercy!"));
+    assert_eq!(state.monsters_killed, 0);
+    assert_eq!(state.strength, 45);
+  }
+
+  #[test]
+  fn test_running_away() {
+    let (mut state, script) = enter_werewolf_lair();
+
+    state.rng.queue(&[0.0]);
+    script.push_inputs(&["y"]);
+    script.run_until_blocked(&mut state);
+    assert_eq!(state.prompt(), FLEE_PROMPT);
+    assert_eq!(state.curr_mode.name(), "combat");
+
+    script.push_inputs(&["w"]);
+    script.run_until_blocked(&mut state);
+
+    let hallway = state.map.find_room("Hallway").unwrap();
+    assert_eq!(state.curr_room, state.map.entrance);
+    assert_eq!(state.curr_mode, GameMode::Primary);
+    assert!(state.map.room(hallway).contents.is_some());
   }
 }
@@ -0,0 +1,354 @@
+use monsters::{Monster, MonsterId};
+use inventory::Inventory;
+use items::{Catalogue, ItemId};
+use rng::Rng;
+
+use self::CombatPhase::*;
+
+// The rules of combat, kept apart from how a fight is shown to the
+// player. Nothing here prints, pauses or touches the rest of the game:
+// each step takes the current state of the fight and returns the next
+// one, along with a list of what happened, which the caller can narrate
+// however it likes (or ignore, if it's only interested in the odds).
+
+const CHANCE_TO_ATTACK_FIRST: f32 = 0.5;
+const CHANCE_TO_WOUND_MONSTER: f32 = 0.5;
+const CHANCE_TO_BE_WOUNDED: f32 = 0.5;
+const CHANCE_OF_BATTLE_ENDING: f32 = 0.35;
+
+// The aftermath is decided by a roll in [0, AFTERMATH_ROLL), which the
+// player wins if it's higher than the danger level.
+const AFTERMATH_ROLL: i32 = 16;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CombatPhase {
+  Preparation,
+  Battle,
+  Aftermath,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CombatState {
+  phase: CombatPhase,
+  enemy: MonsterId,
+  ff: i32,
+
+  // Copied from the monster, so that the rules don't need to look it
+  // up again.
+  damage: i32,
+  flee_chance: f32,
+  loot: (u8, u8),
+}
+
+// A fraction that an item multiplies the danger level by, e.g. 3/4.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Modifier {
+  pub num: i32,
+  pub den: i32,
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+  if b == 0 { a } else { gcd(b, a % b) }
+}
+
+impl Modifier {
+  pub fn new(num: i32, den: i32) -> Self {
+    let divisor = gcd(num, den).max(1);
+    Self { num: num / divisor, den: den / divisor }
+  }
+
+  // Like the book, this divides first, so e.g. 3/4 of 10 is 6.
+  pub fn apply(&self, ff: i32) -> i32 {
+    self.num * (ff / self.den)
+  }
+
+  pub fn combine(&self, other: &Modifier) -> Self {
+    Self::new(self.num * other.num, self.den * other.den)
+  }
+}
+
+// The items that affect how a fight goes.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Equipment {
+  pub weapons: Vec<Modifier>,
+  pub armor: Vec<Modifier>,
+}
+
+impl Equipment {
+  pub fn bare_hands() -> Self {
+    Self::default()
+  }
+
+  pub fn from_items<I>(items: I, catalogue: &Catalogue) -> Self
+    where I: IntoIterator<Item = ItemId>
+  {
+    let mut equipment = Self::bare_hands();
+    for id in items {
+      let item = catalogue.get(id);
+      equipment.weapons.extend(item.weapon);
+      equipment.armor.extend(item.armor);
+    }
+    equipment
+  }
+
+  pub fn from_inventory(items: &Inventory, catalogue: &Catalogue) -> Self {
+    Self::from_items(items.owned(), catalogue)
+  }
+
+  // Applies the equipment to a monster's ferocity factor, returning
+  // the danger level of fighting it. Armor is always worn, while
+  // several weapons are wielded together as if they were one.
+  pub fn modify_ff(&self, base_ff: i32) -> i32 {
+    let ff = self.armor.iter().fold(base_ff, |ff, armor| armor.apply(ff));
+    let mut weapons = self.weapons.iter();
+    match weapons.next() {
+      None => ff + (ff / 5),
+      Some(first) => {
+        weapons.fold(*first, |all, weapon| all.combine(weapon)).apply(ff)
+      },
+    }
+  }
+}
+
+// Something that happened during a step of a fight.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CombatEvent {
+  Fled,
+  FailedToFlee,
+  MonsterAttacks,
+  PlayerAttacks,
+  MonsterWounded,
+  PlayerWounded,
+  KillingBlow,
+  Won,
+  DroppedLoot(u8),
+  Lost,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CombatStep {
+  // The state of the fight afterwards, or None if it's over.
+  pub state: Option<CombatState>,
+  pub strength: i32,
+  pub events: Vec<CombatEvent>,
+}
+
+impl CombatState {
+  pub fn new(enemy: MonsterId, monster: &Monster,
+             equipment: &Equipment) -> Self {
+    Self {
+      phase: Preparation,
+      enemy,
+      ff: equipment.modify_ff(monster.ferocity),
+      damage: monster.damage,
+      flee_chance: monster.flee_chance,
+      loot: monster.loot,
+    }
+  }
+
+  pub fn phase(&self) -> CombatPhase {
+    self.phase
+  }
+
+  pub fn enemy(&self) -> MonsterId {
+    self.enemy
+  }
+
+  pub fn danger_level(&self) -> i32 {
+    self.ff
+  }
+
+  // Tries to run away before the battle starts. If it doesn't work,
+  // the player still has to fight.
+  pub fn run_away(&self, strength: i32, rng: &mut Rng) -> CombatStep {
+    if rng.random() <= self.flee_chance {
+      CombatStep { state: None, strength, events: vec![CombatEvent::Fled] }
+    } else {
+      CombatStep {
+        state: Some(*self),
+        strength,
+        events: vec![CombatEvent::FailedToFlee],
+      }
+    }
+  }
+
+  // Advances the fight by one step: starting the battle if it hasn't
+  // started yet, fighting a round of it, or deciding who won.
+  pub fn fight(&self, strength: i32, rng: &mut Rng) -> CombatStep {
+    match self.phase {
+      Preparation => CombatStep {
+        state: Some(Self { phase: Battle, .. *self }),
+        strength,
+        events: vec![],
+      },
+      Battle => self.fight_round(strength, rng),
+      Aftermath => self.decide_winner(strength, rng),
+    }
+  }
+
+  fn fight_round(&self, mut strength: i32, rng: &mut Rng) -> CombatStep {
+    let mut ff = self.ff;
+    let mut phase = self.phase;
+    let mut events = Vec::new();
+
+    if rng.random() > CHANCE_TO_ATTACK_FIRST {
+      events.push(CombatEvent::MonsterAttacks);
+    } else {
+      events.push(CombatEvent::PlayerAttacks);
+    }
+    if rng.random() > CHANCE_TO_WOUND_MONSTER {
+      events.push(CombatEvent::MonsterWounded);
+      ff = 5 * ff / 6;
+    }
+    if rng.random() > CHANCE_TO_BE_WOUNDED {
+      strength -= self.damage;
+      if strength > 0 {
+        events.push(CombatEvent::PlayerWounded);
+      } else {
+        // Nobody gets up from a killing blow, so the fight is over.
+        events.push(CombatEvent::KillingBlow);
+        return CombatStep { state: None, strength, events };
+      }
+    }
+    if rng.random() <= CHANCE_OF_BATTLE_ENDING {
+      phase = Aftermath;
+    }
+    CombatStep {
+      state: Some(Self { phase, ff, .. *self }),
+      strength,
+      events,
+    }
+  }
+
+  fn decide_winner(&self, strength: i32, rng: &mut Rng) -> CombatStep {
+    if rng.random_i32(0, AFTERMATH_ROLL) > self.ff {
+      let mut events = vec![CombatEvent::Won];
+      let (min, max) = self.loot;
+      if max > 0 {
+        let amount = rng.random_i32(min as i32, max as i32 + 1);
+        events.push(CombatEvent::DroppedLoot(amount as u8));
+      }
+      CombatStep { state: None, strength, events }
+    } else {
+      CombatStep {
+        state: None,
+        strength: strength / 2,
+        events: vec![CombatEvent::Lost],
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use monsters::Bestiary;
+
+  const BARE_HANDS: Equipment = Equipment {
+    weapons: Vec::new(),
+    armor: Vec::new(),
+  };
+
+  fn start_fight(key: &str, equipment: &Equipment) -> CombatState {
+    let bestiary = Bestiary::classic();
+    let id = bestiary.find(key).unwrap();
+    CombatState::new(id, bestiary.get(id), equipment)
+  }
+
+  #[test]
+  fn test_equipment_lowers_danger_level() {
+    let catalogue = Catalogue::classic();
+    let full = Equipment::from_items(catalogue.ids(), &catalogue);
+    assert_eq!(full.weapons, vec![Modifier::new(4, 5), Modifier::new(3, 4)]);
+    assert_eq!(full.armor, vec![Modifier::new(3, 4)]);
+    assert_eq!(Modifier::new(12, 20), Modifier { num: 3, den: 5 });
+    assert_eq!(start_fight("dragon", &BARE_HANDS).danger_level(), 24);
+    assert_eq!(start_fight("dragon", &full).danger_level(), 9);
+  }
+
+  #[test]
+  fn test_fighting_until_victory() {
+    let mut rng = Rng::new(1);
+    let state = start_fight("werewolf", &BARE_HANDS);
+
+    let step = state.fight(100, &mut rng);
+    assert_eq!(step.state.unwrap().phase(), Battle);
+    assert!(step.events.is_empty());
+
+    // You attack and wound the monster, it wounds you, and the battle
+    // ends.
+    rng.queue(&[0.0, 0.99, 0.99, 0.0]);
+    let step = step.state.unwrap().fight(step.strength, &mut rng);
+    assert_eq!(step.events, vec![CombatEvent::PlayerAttacks,
+                                 CombatEvent::MonsterWounded,
+                                 CombatEvent::PlayerWounded]);
+    assert_eq!(step.strength, 95);
+    let state = step.state.unwrap();
+    assert_eq!(state.phase(), Aftermath);
+    assert_eq!(state.danger_level(), 5);
+
+    rng.queue(&[0.99]);
+    let step = state.fight(step.strength, &mut rng);
+    assert_eq!(step, CombatStep {
+      state: None,
+      strength: 95,
+      events: vec![CombatEvent::Won],
+    });
+  }
+
+  #[test]
+  fn test_losing_halves_strength() {
+    let mut rng = Rng::new(1);
+    let state = CombatState {
+      phase: Aftermath,
+      .. start_fight("dragon", &BARE_HANDS)
+    };
+    rng.queue(&[0.99]);
+    let step = state.fight(41, &mut rng);
+    assert_eq!(step.strength, 20);
+    assert_eq!(step.events, vec![CombatEvent::Lost]);
+  }
+
+  #[test]
+  fn test_defeated_monsters_drop_loot() {
+    let mut rng = Rng::new(1);
+    let mut imp = Monster::new("imp");
+    imp.loot = (5, 10);
+    let state = CombatState {
+      phase: Aftermath,
+      .. CombatState::new(MonsterId(0), &imp, &BARE_HANDS)
+    };
+    rng.queue(&[0.99, 0.0]);
+    let step = state.fight(100, &mut rng);
+    assert_eq!(step.events, vec![CombatEvent::Won,
+                                 CombatEvent::DroppedLoot(5)]);
+  }
+
+  #[test]
+  fn test_killing_blow() {
+    let mut rng = Rng::new(1);
+    let state = CombatState {
+      phase: Battle,
+      .. start_fight("dragon", &BARE_HANDS)
+    };
+    rng.queue(&[0.0, 0.0, 0.99]);
+    let step = state.fight(5, &mut rng);
+    assert_eq!(step.strength, 0);
+    assert_eq!(step.state, None);
+    assert_eq!(step.events, vec![CombatEvent::PlayerAttacks,
+                                 CombatEvent::KillingBlow]);
+  }
+
+  #[test]
+  fn test_running_away() {
+    let mut rng = Rng::new(1);
+    let state = start_fight("werewolf", &BARE_HANDS);
+    rng.queue(&[0.99, 0.0]);
+    let step = state.run_away(100, &mut rng);
+    assert_eq!(step.state, Some(state));
+    assert_eq!(step.events, vec![CombatEvent::FailedToFlee]);
+    let step = state.run_away(100, &mut rng);
+    assert_eq!(step.state, None);
+    assert_eq!(step.events, vec![CombatEvent::Fled]);
+  }
+}
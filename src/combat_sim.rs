@@ -0,0 +1,169 @@
+use monsters::{Bestiary, MonsterId};
+use items::{Catalogue, ItemId};
+use combat_engine::{CombatState, CombatPhase, CombatEvent, Equipment};
+use rng::Rng;
+
+// Every fight starts with the player at full strength.
+const INITIAL_STRENGTH: i32 = 100;
+
+// The combined results of many fights against the same monster, with
+// the same equipment.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FightStats {
+  pub monster: MonsterId,
+  pub items: Vec<ItemId>,
+  pub danger_level: i32,
+  pub fights: u32,
+  pub wins: u32,
+  pub strength_lost: i32,
+  pub turns: u32,
+}
+
+impl FightStats {
+  pub fn win_chance(&self) -> f32 {
+    self.wins as f32 / self.fights.max(1) as f32
+  }
+
+  pub fn expected_strength_loss(&self) -> f32 {
+    self.strength_lost as f32 / self.fights.max(1) as f32
+  }
+
+  pub fn expected_turns(&self) -> f32 {
+    self.turns as f32 / self.fights.max(1) as f32
+  }
+}
+
+// Runs a single fight through the combat engine, without ever running
+// away, and returns whether the player won, how much strength they
+// lost and how many turns the battle took.
+fn fight(state: CombatState, rng: &mut Rng) -> (bool, i32, u32) {
+  let mut state = Some(state);
+  let mut strength = INITIAL_STRENGTH;
+  let mut won = false;
+  let mut turns = 0;
+  while let Some(curr) = state {
+    if curr.phase() == CombatPhase::Battle {
+      turns += 1;
+    }
+    let step = curr.fight(strength, rng);
+    won = step.events.contains(&CombatEvent::Won);
+    strength = step.strength;
+    state = step.state;
+  }
+  (won, INITIAL_STRENGTH - strength, turns)
+}
+
+// Fights the given monster with the given items many times, using
+// consecutive seeds starting from the given one.
+pub fn simulate(bestiary: &Bestiary, monster: MonsterId, catalogue: &Catalogue,
+                items: &[ItemId], fights: u32, seed: u64) -> FightStats {
+  let equipment = Equipment::from_items(items.iter().cloned(), catalogue);
+  let state = CombatState::new(monster, bestiary.get(monster), &equipment);
+  let mut stats = FightStats {
+    monster,
+    items: items.to_vec(),
+    danger_level: state.danger_level(),
+    fights,
+    wins: 0,
+    strength_lost: 0,
+    turns: 0,
+  };
+  for i in 0..fights {
+    let mut rng = Rng::new(seed.wrapping_add(i as u64));
+    let (won, strength_lost, turns) = fight(state, &mut rng);
+    if won {
+      stats.wins += 1;
+    }
+    stats.strength_lost += strength_lost;
+    stats.turns += turns;
+  }
+  stats
+}
+
+// Every combination of the items that affect combat, from bare hands
+// to fully equipped.
+pub fn loadouts(catalogue: &Catalogue) -> Vec<Vec<ItemId>> {
+  let gear: Vec<ItemId> = catalogue.ids().filter(|&id| {
+    catalogue.get(id).affects_combat()
+  }).collect();
+  (0..1 << gear.len()).map(|bits: usize| {
+    gear.iter().enumerate().filter(|&(i, _)| bits & (1 << i) != 0)
+      .map(|(_, &id)| id).collect()
+  }).collect()
+}
+
+// Names the items, e.g. "axe+sword".
+fn describe_loadout(catalogue: &Catalogue, items: &[ItemId]) -> String {
+  if items.is_empty() {
+    String::from("bare hands")
+  } else {
+    let nouns: Vec<&str> = items.iter().map(|&id| {
+      catalogue.get(id).noun.as_str()
+    }).collect();
+    nouns.join("+")
+  }
+}
+
+// Simulates every combination of monster and equipment.
+pub fn simulate_all(bestiary: &Bestiary, catalogue: &Catalogue, fights: u32,
+                    seed: u64) -> Vec<FightStats> {
+  let mut results = Vec::new();
+  for monster in bestiary.ids() {
+    for items in loadouts(catalogue) {
+      results.push(simulate(bestiary, monster, catalogue, &items, fights,
+                            seed));
+    }
+  }
+  results
+}
+
+// Formats the results as a table, one row per monster and equipment.
+pub fn describe(bestiary: &Bestiary, catalogue: &Catalogue,
+                results: &[FightStats]) -> Vec<String> {
+  let width = bestiary.ids().map(|id| {
+    bestiary.get(id).name.len()
+  }).max().unwrap_or(0);
+  let loadouts: Vec<String> = results.iter().map(|stats| {
+    describe_loadout(catalogue, &stats.items)
+  }).collect();
+  let loadout_width = loadouts.iter().map(|loadout| loadout.len())
+    .fold(18, usize::max);
+  let mut lines = vec![
+    format!("{:<w$} {:<lw$} {:>6} {:>6} {:>9} {:>6}",
+            "Monster", "Equipment", "Danger", "Win %", "Str lost", "Turns",
+            w = width, lw = loadout_width),
+  ];
+  for (stats, loadout) in results.iter().zip(loadouts) {
+    lines.push(format!("{:<w$} {:<lw$} {:>6} {:>6.1} {:>9.1} {:>6.2}",
+                       bestiary.get(stats.monster).name, loadout,
+                       stats.danger_level, 100.0 * stats.win_chance(),
+                       stats.expected_strength_loss(),
+                       stats.expected_turns(), w = width,
+                       lw = loadout_width));
+  }
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_equipment_helps_against_dragons() {
+    let bestiary = Bestiary::classic();
+    let catalogue = Catalogue::classic();
+    let dragon = bestiary.find("dragon").unwrap();
+    let loadouts = loadouts(&catalogue);
+    assert_eq!(loadouts.len(), 8);
+    assert_eq!(describe_loadout(&catalogue, &loadouts[7]), "axe+sword+armor");
+    let bare = simulate(&bestiary, dragon, &catalogue, &loadouts[0], 500, 1);
+    let full = simulate(&bestiary, dragon, &catalogue, &loadouts[7], 500, 1);
+
+    assert_eq!(bare.danger_level, 24);
+    assert_eq!(full.danger_level, 9);
+    assert!(bare.win_chance() < 0.1);
+    assert!(full.win_chance() > 0.4);
+    assert!(full.expected_strength_loss() < bare.expected_strength_loss());
+    assert!(bare.expected_turns() >= 1.0);
+  }
+}
@@ -1,21 +1,52 @@
-use std::ascii::AsciiExt;
+use util;
+use word_parser;
+use game_state::GameState;
 
+// Menus that depend on the game, like the shop's, can name the state,
+// e.g. `command_processor!(InventoryCommand, |state| { ... })`.
 macro_rules! command_processor {
   ( $command_enum:path, $block:block ) => {
+    command_processor!($command_enum, |_state| $block);
+  };
+  ( $command_enum:path, |$state:ident| $block:block ) => {
     use command::CommandProcessor;
 
     impl CommandProcessor<$command_enum> for $command_enum {
-      fn get_command_info() -> Vec<CommandInfo<$command_enum>> $block
+      fn get_command_info(
+        $state: &::game_state::GameState
+      ) -> Vec<CommandInfo<$command_enum>> $block
     }
-  }
+  };
+}
+
+// How the player's input is turned into commands.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ParserMode {
+  // Only the first character of the input matters, just like in the
+  // original BASIC program. This means that e.g. "eat" goes east.
+  Classic,
+
+  // Whole words are understood, e.g. "go north" or "eat 3 food". A
+  // single character still works the same way as in classic mode.
+  Words,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ParsedCommand<T: Copy> {
+  pub cmd: T,
+
+  // The number the player typed along with the command, if any, e.g.
+  // the 3 in "eat 3 food". This is always None in classic mode.
+  pub amount: Option<i32>,
 }
 
 #[derive(Clone)]
 pub struct CommandInfo<T: Copy> {
-  key: char,
-  desc: String,
-  cmd: T,
-  hidden: bool,
+  pub key: char,
+  pub desc: String,
+  pub cmd: T,
+  pub hidden: bool,
+  pub phrases: Vec<String>,
 }
 
 impl<T: Copy> CommandInfo<T> {
@@ -25,7 +56,8 @@ impl<T: Copy> CommandInfo<T> {
       // TODO: We might be making unnecessary copies of strings here.
       desc: String::from(desc.as_ref()),
       cmd,
-      hidden: false
+      hidden: false,
+      phrases: Vec::new(),
     }
   }
 
@@ -33,43 +65,140 @@ impl<T: Copy> CommandInfo<T> {
     self.hidden = true;
     self
   }
+
+  // Sets the phrases that trigger the command when using the word
+  // parser. Synonyms and filler words are taken care of by the parser,
+  // so e.g. "go north" also covers "walk to the north".
+  pub fn phrases<S: AsRef<str>>(mut self, phrases: &[S]) -> Self {
+    self.phrases = phrases.iter().map(|phrase| {
+      String::from(phrase.as_ref())
+    }).collect();
+    self
+  }
 }
 
 pub trait CommandProcessor<T: Copy> {
-  fn from_char(c: char) -> Option<T> {
+  fn from_char(state: &GameState, c: char) -> Option<T> {
     // A HashMap here would obviously be more efficient, but
     // since we're not going to be called very often, it's probably
     // not that big a deal.
-    for info in Self::get_command_info().iter() {
+    for info in Self::get_command_info(state).iter() {
       if c == info.key { return Some(info.cmd); }
     }
     None
   }
 
-  fn get_command_info() -> Vec<CommandInfo<T>>;
+  fn get_command_info(state: &GameState) -> Vec<CommandInfo<T>>;
 
-  fn show_help() {
-    for info in Self::get_command_info().iter().filter(|i| !i.hidden) {
-      wrapln!("  {} - {}", info.key, info.desc);
+  fn show_help(state: &mut GameState) {
+    for info in Self::get_command_info(state).iter().filter(|i| !i.hidden) {
+      match info.phrases.first() {
+        Some(phrase) if state.parser == ParserMode::Words => {
+          wrapln!(state, "  {}, {} - {}", info.key, phrase, info.desc)
+        },
+        _ => wrapln!(state, "  {} - {}", info.key, info.desc),
+      }
     }
   }
 
-  fn get_from_input(input: String) -> Option<T> {
-    match input.chars().next() {
-      Some(k) => {
-        let k = k.to_ascii_lowercase();
-        if k == 'h' || k == '?' {
-          wrapln!("Here's what I understand right now:\n");
-          Self::show_help();
-          wrapln!();
+  fn explain_commands(state: &mut GameState) {
+    wrapln!(state, "Here's what I understand right now:\n");
+    Self::show_help(state);
+    wrapln!(state);
+  }
+
+  // Figures out which command the player meant, using the game's
+  // parser mode. If it's not clear, this explains why to the player
+  // and returns None.
+  fn get_from_input(state: &mut GameState,
+                    input: String) -> Option<ParsedCommand<T>> {
+    let tokens = match state.parser {
+      ParserMode::Classic => None,
+      ParserMode::Words => match word_parser::tokenize(&input) {
+        Ok(tokens) => Some(tokens),
+        Err(message) => {
+          wrapln!(state, "{}", message);
           return None;
-        } else if let Some(cmd) = Self::from_char(k) {
-          return Some(cmd);
         }
       },
-      None => {}
+    };
+    let is_phrase = |words: &[String]| {
+      words.len() > 1 || (words.len() == 1 && words[0].chars().count() > 1)
+    };
+
+    match tokens {
+      Some(ref tokens) if tokens.words == ["help"] => {
+        Self::explain_commands(state);
+        None
+      },
+      Some(ref tokens) if is_phrase(&tokens.words) => {
+        Self::get_from_words(state, &tokens.words).map(|cmd| {
+          ParsedCommand { cmd, amount: tokens.amount }
+        })
+      },
+      _ => {
+        // If the player typed nothing but a number, it's the key of a
+        // command rather than an amount.
+        let amount = tokens.and_then(|tokens| {
+          if tokens.words.is_empty() { None } else { tokens.amount }
+        });
+        Self::get_from_first_char(state, &input).map(|cmd| {
+          ParsedCommand { cmd, amount }
+        })
+      },
+    }
+  }
+
+  fn get_from_first_char(state: &mut GameState, input: &str) -> Option<T> {
+    let input = if state.parser == ParserMode::Words {
+      input.trim()
+    } else {
+      input
+    };
+    if let Some(k) = input.chars().next() {
+      let k = k.to_ascii_lowercase();
+      if k == 'h' || k == '?' {
+        Self::explain_commands(state);
+        return None;
+      } else if let Some(cmd) = Self::from_char(state, k) {
+        return Some(cmd);
+      }
+    }
+    wrapln!(state, "I have no idea what you're talking about.");
+    None
+  }
+
+  // Finds the command whose phrase matches the given words. Exact
+  // matches win; otherwise abbreviations like "ge tr" are accepted as
+  // long as they only match one command.
+  fn get_from_words(state: &mut GameState, words: &[String]) -> Option<T> {
+    let infos = Self::get_command_info(state);
+    let find = |allow_prefixes| -> Vec<&CommandInfo<T>> {
+      infos.iter().filter(|info| {
+        info.phrases.iter().any(|phrase| {
+          word_parser::matches_phrase(words, phrase, allow_prefixes)
+        })
+      }).collect()
+    };
+    let mut matches = find(false);
+    if matches.is_empty() {
+      matches = find(true);
+    }
+
+    match matches.len() {
+      0 => {
+        wrapln!(state, "I have no idea what you're talking about.");
+        None
+      },
+      1 => Some(matches[0].cmd),
+      _ => {
+        let descs = matches.iter().map(|info| {
+          format!("'{}'", info.desc)
+        }).collect::<Vec<_>>();
+        wrapln!(state, "Did you mean {}?", util::friendly_join_with(
+          descs.iter().map(|desc| desc.as_str()).collect(), "or"));
+        None
+      },
     }
-    wrapln!("I have no idea what you're talking about.");
-    return None;
   }
 }
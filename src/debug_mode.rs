@@ -1,40 +1,57 @@
-use sized_enum::SizedEnum;
-use enum_primitive::FromPrimitive;
 use map::RoomId;
 use game_state::{GameState, GameMode};
 
 impl GameState {
-  fn print_help(&self) {
+  fn print_help(&mut self) {
     wrapln!(
+      self,
       "Debug mode commands:\n\n\
-
        quit            - exit debug mode\n\
-       goto <room id>  - teleport to a room\n\
+       goto <room>     - teleport to a room (by number or key)\n\
        rooms           - list rooms + their contents\n\
-       version         - show version number\n"
+       validate        - check the map for problems\n\
+       version         - show version number and random seed\n"
     );
   }
 
   fn goto_room(&mut self, args: Vec<&str>) {
-    if let Ok(number) = args[0].parse::<usize>() {
-      if let Some(room_id) = RoomId::from_usize(number) {
-        self.curr_room = room_id;
-        wrapln!("Teleported to {:?}.", room_id);
+    let name = match args.first() {
+      Some(name) => *name,
+      None => {
+        wrapln!(self, "Usage: goto <room>");
         return;
+      },
+    };
+    let room_id = match name.parse::<usize>() {
+      Ok(number) if number < self.map.num_rooms() => Some(RoomId(number)),
+      Ok(_) => None,
+      Err(_) => self.map.find_room(name),
+    };
+    if let Some(room_id) = room_id {
+      self.curr_room = room_id;
+      wrapln!(self, "Teleported to {}.", self.map.room(room_id).key);
+    } else {
+      wrapln!(self, "Unknown room.");
+    }
+  }
+
+  fn list_rooms(&mut self) {
+    for room_id in self.map.room_ids() {
+      let room = self.map.room(room_id);
+      wrapln!(self.platform, "Room {} - {}", room_id.0, room.key);
+      if let Some(contents) = room.contents {
+        wrapln!(self.platform, "  Contains {:?}", contents);
       }
     }
-    wrapln!("Unknown room id.");
   }
 
-  fn list_rooms(&self) {
-    for i in 0..RoomId::size() {
-      RoomId::from_usize(i).map(|room_id| {
-        let room = self.map.room(room_id);
-        wrapln!("Room {} - {:?}", i, room_id);
-        if let Some(contents) = room.contents {
-          wrapln!("  Contains {:?}", contents);
-        }
-      });
+  fn validate_map(&mut self) {
+    let problems = self.map.validate().describe(&self.map);
+    if problems.is_empty() {
+      wrapln!(self, "No problems found.");
+    }
+    for problem in problems {
+      wrapln!(self, "{}", problem);
     }
   }
 
@@ -46,13 +63,31 @@ impl GameState {
         state.print_help();
       } else if input == "rooms" {
         state.list_rooms();
+      } else if input == "validate" {
+        state.validate_map();
       } else if input.starts_with("goto ") {
         state.goto_room(input.split_whitespace().skip(1).collect());
       } else if input == "version" {
-        wrapln!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-      } else if input.len() > 0 {
-        wrapln!("Unrecognized command. Type ? for help.");
+        wrapln!(state, "{} {} (seed {})", env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"), state.rng.seed());
+      } else if !input.is_empty() {
+        wrapln!(state, "Unrecognized command. Type ? for help.");
       }
     });
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use platform::scripted::*;
+
+  #[test]
+  fn test_goto_needs_a_room() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "`", "goto ", "goto Hallway"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output().contains("Usage: goto <room>"));
+    assert_eq!(state.curr_room, state.map.find_room("Hallway").unwrap());
+  }
+}
@@ -4,10 +4,7 @@ use sized_enum::SizedEnum;
 
 use self::Direction::*;
 
-const NUM_DIRECTIONS: usize = 6;
-
-enum_from_primitive! {
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, SizedEnum)]
 pub enum Direction {
   North,
   South,
@@ -16,7 +13,6 @@ pub enum Direction {
   Up,
   Down,
 }
-}
 
 impl Direction {
   pub fn character(self) -> char {
@@ -30,6 +26,19 @@ impl Direction {
     }
   }
 
+  pub fn is_vertical(self) -> bool {
+    self == Up || self == Down
+  }
+
+  // Says which way something is, e.g. "to the north" or "up".
+  pub fn toward(self) -> String {
+    if self.is_vertical() {
+      self.to_string()
+    } else {
+      format!("to the {}", self)
+    }
+  }
+
   pub fn opposite(self) -> Self {
     match self {
       North => South,
@@ -42,10 +51,6 @@ impl Direction {
   }
 }
 
-impl SizedEnum for Direction {
-  fn size() -> usize { NUM_DIRECTIONS }
-}
-
 impl fmt::Display for Direction {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(f, "{}", match *self {
@@ -0,0 +1,133 @@
+use direction::Direction;
+use sized_enum::SizedEnum;
+use items::ItemId;
+use events::GameEvent;
+use game_state::GameState;
+
+// Locked doors open for anyone carrying the item their lock names, and
+// secret passages turn up when the player searches the room they're in.
+impl GameState {
+  // Whether the exit in the given direction is a door that the player
+  // knows about but can't go through yet.
+  pub fn is_exit_locked(&self, dir: Direction) -> bool {
+    self.map.room(self.curr_room).exit(dir).is_some_and(|exit| {
+      exit.locked && !exit.hidden
+    })
+  }
+
+  // The item that unlocks the exit in the given direction, if the shop
+  // sells it.
+  fn key_for_exit(&self, dir: Direction) -> Option<ItemId> {
+    self.map.room(self.curr_room).exit(dir)
+      .and_then(|exit| exit.lock.as_ref())
+      .and_then(|key| self.map.catalogue.find(key))
+  }
+
+  pub fn explain_lock(&mut self, dir: Direction) {
+    wrapln!(self, "The way {} is locked.", dir.toward());
+    if let Some(id) = self.key_for_exit(dir) {
+      let item = self.map.catalogue.get(id).clone();
+      if self.items.owns(id) {
+        wrapln!(self, "You could unlock it with your {}.", item.noun);
+      } else {
+        wrapln!(self, "You'll need {} to open it.", item.name);
+      }
+    }
+  }
+
+  pub fn unlock_doors(&mut self) {
+    let here = self.curr_room;
+    let locked: Vec<Direction> = Direction::iter().filter(|&dir| {
+      self.is_exit_locked(dir)
+    }).collect();
+    if locked.is_empty() {
+      wrapln!(self, "There's nothing to unlock here.");
+    }
+
+    for dir in locked {
+      match self.key_for_exit(dir) {
+        Some(id) if self.items.owns(id) => {
+          let noun = self.map.catalogue.get(id).noun.clone();
+          wrapln!(self, "You unlock the way {} with your {}.",
+                  dir.toward(), noun);
+          self.map.unlock_exit(here, dir);
+          self.emit(GameEvent::ExitUnlocked(here, dir));
+        },
+        _ => { self.explain_lock(dir) },
+      }
+    }
+  }
+
+  pub fn search_room(&mut self) {
+    if !self.can_player_see() {
+      wrapln!(self, "It's too dark to search here.");
+      return;
+    }
+
+    let here = self.curr_room;
+    wrapln!(self, "You search the room carefully...");
+    self.pause();
+    self.process_move();
+
+    let hidden: Vec<Direction> = Direction::iter().filter(|&dir| {
+      self.map.room(here).exit(dir).is_some_and(|exit| exit.hidden)
+    }).collect();
+    if hidden.is_empty() {
+      wrapln!(self, "You don't find anything out of the ordinary.");
+    }
+
+    for dir in hidden {
+      wrapln!(self, "You find a secret passage {}!", dir.toward());
+      self.map.reveal_exit(here, dir);
+      self.explored.see_exit(here, dir);
+      self.emit(GameEvent::SecretFound(here, dir));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use direction::Direction::*;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_searching_finds_secret_passages() {
+    let (mut state, script) = new_empty_game();
+    let bedroom = state.map.find_room("ChambermaidsBedroom").unwrap();
+    state.curr_room = bedroom;
+    give_item(&mut state, "torch", 1);
+    script.push_inputs(&["Alice", "n", "x", "n"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("You can't go that way."));
+    assert!(output.contains("You find a secret passage to the north!"));
+    assert_eq!(state.curr_room,
+               state.map.find_room("DressingChamber").unwrap());
+    assert!(state.explored.has_seen_exit(bedroom, North));
+    assert_eq!(state.strength, 90);
+  }
+
+  #[test]
+  fn test_locked_doors_need_a_key() {
+    let (mut state, script) = new_empty_game();
+    let entrance = state.map.entrance;
+    state.map.lock_exit(entrance, East, "sword");
+    script.push_inputs(&["Alice", "e", "k"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("The way to the east is locked."));
+    assert!(output.contains("You'll need a sword to open it."));
+    assert_eq!(state.curr_room, entrance);
+
+    give_item(&mut state, "sword", 1);
+    script.push_inputs(&["k", "e"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("You unlock the way to the east with your sword."));
+    assert_eq!(state.curr_room, state.map.find_room("Hallway").unwrap());
+    assert!(!state.map.room(state.curr_room).exit(West).unwrap().locked);
+  }
+}
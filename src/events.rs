@@ -0,0 +1,59 @@
+use map::RoomId;
+use direction::Direction;
+use items::ItemId;
+use monsters::MonsterId;
+
+// Something that happened during the game, described in a way that
+// other parts of the program can react to without having to parse
+// what was printed to the player.
+#[derive(Debug, PartialEq, Clone)]
+pub enum GameEvent {
+  RoomEntered(RoomId),
+  TreasurePickedUp(i32),
+  ItemBought(ItemId, i32),
+  ItemFound(ItemId, i32),
+  CombatStarted(MonsterId),
+  CombatWon(MonsterId),
+  CombatLost(MonsterId),
+  Fled(MonsterId),
+  FoodEaten(i32),
+  ExitUnlocked(RoomId, Direction),
+  SecretFound(RoomId, Direction),
+  CheatingAccused,
+  Died,
+  Escaped,
+}
+
+// Observers are only given the event, not the game, so that they can't
+// change the game's state from underneath the code that emitted it.
+pub type EventObserver = dyn FnMut(&GameEvent);
+
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  use super::*;
+  use map::RoomContents::Treasure;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_observers_see_events() {
+    let (mut state, script) = new_empty_game();
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let observed = events.clone();
+    state.add_observer(move |event| {
+      observed.borrow_mut().push(event.clone())
+    });
+    state.map.mut_room(state.map.entrance).contents =
+      Some(Treasure(10));
+    script.push_inputs(&["Alice", "p", "e"]);
+    script.run_until_blocked(&mut state);
+
+    let hallway = state.map.find_room("Hallway").unwrap();
+    assert_eq!(*events.borrow(), vec![
+      GameEvent::TreasurePickedUp(10),
+      GameEvent::RoomEntered(hallway),
+    ]);
+  }
+}
@@ -1,15 +1,21 @@
 use map::{RoomId, Map};
-use combat::CombatState;
+use automap::Exploration;
+use achievements::AchievementTracker;
+use events::{GameEvent, EventObserver};
+use combat_engine::CombatState;
 use inventory::Inventory;
 use direction::Direction;
-use items::Item::*;
-use platform::*;
+use rng::Rng;
+use command::ParserMode;
+use save::SAVE_KEY;
+use high_scores::Outcome;
+use platform::AbstractPlatform;
+use platform::session::SessionPlatform;
 
 const PAUSE_MS: u64 = 2500;
-const CHEATING_FOOD_DIVISOR: i32 = 4;
+const CHEATING_STACK_DIVISOR: i32 = 4;
 const INITIAL_STRENGTH: i32 = 100;
 const INITIAL_WEALTH: i32 = 75;
-const STRENGTH_PER_FOOD: i32 = 10;
 const TALLY_PER_MOVE: i32 = 1;
 const STRENGTH_LOSS_PER_MOVE: i32 = 5;
 
@@ -26,6 +32,23 @@ pub enum GameMode {
   Debug,
 }
 
+impl GameMode {
+  // A short name for the mode, for programs that watch the game.
+  pub fn name(&self) -> &'static str {
+    match *self {
+      GameMode::AskName => "ask_name",
+      GameMode::Primary => "primary",
+      GameMode::Inventory => "inventory",
+      GameMode::EatFood => "eat_food",
+      GameMode::Combat(_) => "combat",
+      GameMode::Finished => "finished",
+
+      #[cfg(debug_assertions)]
+      GameMode::Debug => "debug",
+    }
+  }
+}
+
 // We have our input callbacks have a GameState explicitly passed
 // into them instead of expecting 'self' to be captured into their
 // closure, because the latter leads to all kinds of lifetime
@@ -33,7 +56,7 @@ pub enum GameMode {
 //
 // Note also that this doesn't need to be a FnOnce, since it can
 // request that it be called again, e.g. if the user input was invalid.
-type InputCallback = Fn(&mut GameState, String);
+type InputCallback = dyn Fn(&mut GameState, String);
 
 pub struct GameState {
   pub map: Map,
@@ -46,7 +69,19 @@ pub struct GameState {
   pub monsters_killed: i32,
   pub curr_room: RoomId,
   pub show_desc: bool,
+  pub pending_triggers: bool,
+
+  // Set when a monster has just found the player, who has to fight it
+  // even if they can't see it.
+  pub ambushed: bool,
   pub items: Inventory,
+  pub rng: Rng,
+  pub explored: Exploration,
+  pub achievements: AchievementTracker,
+  pub parser: ParserMode,
+  pub platform: SessionPlatform,
+  observers: Vec<Box<EventObserver>>,
+  offered_resume: bool,
   input_callback: Option<Box<InputCallback>>,
   is_processing_input: bool,
   last_input_prompt: String,
@@ -54,19 +89,29 @@ pub struct GameState {
 }
 
 impl GameState {
-  pub fn new() -> Self {
+  pub fn new(seed: u64, map: Map,
+             platform: Box<dyn AbstractPlatform>) -> Self {
     Self {
-      map: Map::new(),
       player_name: String::from(""),
       curr_mode: GameMode::AskName,
-      curr_room: RoomId::Entrance,
+      curr_room: map.entrance,
+      explored: Exploration::new(map.num_rooms()),
+      achievements: AchievementTracker::new(&map),
+      items: Inventory::new(map.catalogue.len()),
+      map,
       shown_hint: false,
       strength: INITIAL_STRENGTH,
       wealth: INITIAL_WEALTH,
       tally: 0,
       monsters_killed: 0,
       show_desc: true,
-      items: Inventory::new(),
+      pending_triggers: true,
+      ambushed: false,
+      rng: Rng::new(seed),
+      parser: ParserMode::Classic,
+      platform: SessionPlatform::new(platform),
+      observers: Vec::new(),
+      offered_resume: false,
       input_callback: None,
       is_processing_input: false,
       last_input_prompt: String::from(""),
@@ -76,12 +121,21 @@ impl GameState {
 
   pub fn show_prompt(&mut self, prompt: &str) {
     self.last_input_prompt = String::from(prompt);
-    Platform::show_prompt(prompt);
+    self.platform.show_prompt(prompt);
+  }
+
+  // The question the game most recently asked the player.
+  pub fn prompt(&self) -> &str {
+    &self.last_input_prompt
+  }
+
+  pub fn writeln_with_wrapping(&mut self, s: &str) {
+    self.platform.writeln_with_wrapping(s);
   }
 
   pub fn ask_again(&mut self) {
-    assert_eq!(self.is_processing_input, true,
-               "This method must be called from an input callback");
+    assert!(self.is_processing_input,
+            "This method must be called from an input callback");
     self.read_input_again = true;
   }
 
@@ -94,7 +148,7 @@ impl GameState {
           cb(state, amount);
         },
         Err(_) => {
-          wrapln!("That does not even look like a number, {}.",
+          wrapln!(state, "That does not even look like a number, {}.",
                   state.player_name);
           state.ask_again();
         }
@@ -116,8 +170,29 @@ impl GameState {
     self.input_callback = Some(Box::new(cb));
   }
 
+  // Registers a function to be called with every event from now on,
+  // e.g. to log the game or show it in a different kind of UI.
+  pub fn add_observer<F>(&mut self, observer: F)
+      where F: 'static + FnMut(&GameEvent) {
+    self.observers.push(Box::new(observer));
+  }
+
+  pub fn emit(&mut self, event: GameEvent) {
+    self.achievements.observe(&event);
+    for observer in self.observers.iter_mut() {
+      observer(&event);
+    }
+  }
+
   pub fn can_player_see(&self) -> bool {
-    self.curr_room == RoomId::Entrance || self.items.owns(Torch)
+    self.curr_room == self.map.entrance || self.items.owned().any(|id| {
+      self.map.catalogue.get(id).light
+    })
+  }
+
+  // How many units of food the player has, if the shop sells any.
+  pub fn food(&self) -> i32 {
+    self.map.catalogue.food().map_or(0, |id| self.items.get_quantity(id))
   }
 
   pub fn set_mode(&mut self, mode: GameMode) {
@@ -125,32 +200,31 @@ impl GameState {
     self.curr_mode = mode;
   }
 
-  pub fn print_wealth(&self) {
+  pub fn print_wealth(&mut self) {
     if self.wealth > 0 {
-      wrapln!("You have ${}.", self.wealth);
+      wrapln!(self, "You have ${}.", self.wealth);
     } else {
-      wrapln!("You have no money.");
+      wrapln!(self, "You have no money.");
     }
   }
 
-  pub fn print_food(&self) {
-    let food = self.items.get_quantity(Food);
-    wrapln!("Your provisions sack holds {} unit{} of food.",
-            food, if food == 1 { "" } else { "s" });
-  }
-
-  fn get_score(&self) -> i32 {
-    3  * self.tally +
-    5  * self.strength +
-    2  * self.wealth +
-    1  * self.items.get_quantity(Food) +
-    30 * self.monsters_killed
+  pub fn print_food(&mut self) {
+    let food = self.food();
+    let noun = self.map.catalogue.food().map_or(String::from("food"), |id| {
+      self.map.catalogue.get(id).noun.clone()
+    });
+    wrapln!(self, "Your provisions sack holds {} unit{} of {}.",
+            food, if food == 1 { "" } else { "s" }, noun);
   }
 
-  pub fn finish_game(&mut self) {
+  pub fn finish_game(&mut self, outcome: Outcome) {
     self.curr_mode = GameMode::Finished;
-    wrapln!("Your score is {}.\n", self.get_score());
-    wrapln!("Farewell.");
+    let breakdown = self.get_score_breakdown(Some(outcome));
+    self.print_score_breakdown(&breakdown);
+    wrapln!(self, "Your score is {}.\n", breakdown.total());
+    self.print_achievements();
+    self.record_high_score(outcome, breakdown.total());
+    wrapln!(self, "Farewell.");
   }
 
   pub fn is_finished(&self) -> bool {
@@ -158,34 +232,42 @@ impl GameState {
   }
 
   pub fn accuse_player_of_cheating(&mut self) {
-    wrapln!("YOU HAVE TRIED TO CHEAT ME!");
+    wrapln!(self, "YOU HAVE TRIED TO CHEAT ME!");
+    self.emit(GameEvent::CheatingAccused);
     self.wealth = 0;
-    self.items.lose(Torch);
-    self.items.lose(Axe);
-    self.items.lose(Sword);
-
-    let food = self.items.get_quantity(Food);
-    self.items.set_quantity(Food, food / CHEATING_FOOD_DIVISOR);
-
-    self.items.lose(Amulet);
-    self.items.lose(Armor);
-    Self::pause();
+    for id in self.map.catalogue.ids() {
+      if self.map.catalogue.get(id).stackable {
+        let quantity = self.items.get_quantity(id);
+        self.items.set_quantity(id, quantity / CHEATING_STACK_DIVISOR);
+      } else {
+        self.items.lose(id);
+      }
+    }
+    self.pause();
   }
 
-  pub fn pause() {
-    Platform::sleep(PAUSE_MS);
+  pub fn pause(&mut self) {
+    self.platform.sleep(PAUSE_MS);
   }
 
   fn die(&mut self) {
-    wrapln!("You have died.........");
-    Self::pause();
-    self.finish_game();
+    wrapln!(self, "You have died.........");
+    self.pause();
+    self.emit(GameEvent::Died);
+    self.finish_game(Outcome::Died);
   }
 
   fn tick_ask_name_mode(&mut self) {
+    if !self.offered_resume {
+      self.offered_resume = true;
+      if let Some(data) = self.platform.load_data(SAVE_KEY) {
+        self.ask_to_resume(data);
+        return;
+      }
+    }
     self.ask("What is your name, explorer? ", |state, input| {
-      if input.len() == 0 {
-        wrapln!("Pardon me?");
+      if input.is_empty() {
+        wrapln!(state, "Pardon me?");
       } else {
         state.player_name = input;
         state.set_mode(GameMode::Primary);
@@ -196,44 +278,74 @@ impl GameState {
   fn tick_eat_food_mode(&mut self) {
     if self.show_desc {
       self.print_food();
-      wrapln!();
+      wrapln!(self);
       self.show_desc = false;
     }
 
     self.ask_i32("How many do you want to eat? ", |state, amount| {
       if amount < 0 {
-        wrapln!("GIVE ME A POSITIVE INTEGER.");
+        wrapln!(state, "GIVE ME A POSITIVE INTEGER.");
         state.ask_again();
-      } else if amount == 0 {
-        wrapln!("Fine, be that way.");
-        Self::pause();
-        state.set_mode(GameMode::Primary);
-      } else if amount > state.items.get_quantity(Food) {
-        state.accuse_player_of_cheating();
-        state.set_mode(GameMode::Primary);
       } else {
-        wrapln!("After some munching, you feel stronger.");
-        state.items.decrease(Food, amount);
-        state.strength += amount * STRENGTH_PER_FOOD;
-        state.set_mode(GameMode::Primary);
-        Self::pause();
+        state.eat_food(amount);
       }
     });
   }
 
+  pub fn eat_food(&mut self, amount: i32) {
+    if amount < 0 {
+      wrapln!(self, "GIVE ME A POSITIVE INTEGER.");
+    } else if amount == 0 {
+      wrapln!(self, "Fine, be that way.");
+      self.pause();
+    } else if amount > self.food() {
+      self.accuse_player_of_cheating();
+    } else if let Some(id) = self.map.catalogue.food() {
+      wrapln!(self, "After some munching, you feel stronger.");
+      self.items.decrease(id, amount);
+      self.strength += amount * self.map.catalogue.get(id).nourishment();
+      self.emit(GameEvent::FoodEaten(amount));
+      self.pause();
+    }
+    self.set_mode(GameMode::Primary);
+  }
+
+  // Takes a turn, which wears the player out and gives the monsters
+  // a chance to move. Call this after the player has moved, so that
+  // monsters can head towards where they are now.
   pub fn process_move(&mut self) {
     self.tally += TALLY_PER_MOVE;
     self.strength -= STRENGTH_LOSS_PER_MOVE;
+    self.move_monsters();
+  }
+
+  // Moves the player into the given room, which will be described (and
+  // its triggers fired) the next time the game ticks.
+  pub fn enter_room(&mut self, room_id: RoomId) {
+    self.curr_room = room_id;
+    self.show_desc = true;
+    self.pending_triggers = true;
+    self.ambushed = false;
+    self.emit(GameEvent::RoomEntered(room_id));
   }
 
   pub fn try_to_move(&mut self, dir: Direction) -> bool {
-    if let Some(room) = self.map.room(self.curr_room).get_exit(dir) {
-      self.curr_room = room;
-      self.show_desc = true;
+    if self.is_exit_locked(dir) {
+      self.explain_lock(dir);
+      false
+    } else if let Some(room) = self.map.room(self.curr_room).get_exit(dir) {
+      // The player can always find their way back the way they came,
+      // even in the dark.
+      let prev_room = self.curr_room;
+      self.explored.see_exit(prev_room, dir);
+      if self.map.room(room).get_exit(dir.opposite()) == Some(prev_room) {
+        self.explored.see_exit(room, dir.opposite());
+      }
+      self.enter_room(room);
       self.process_move();
       true
     } else {
-      wrapln!("You can't go that way.");
+      wrapln!(self, "You can't go that way.");
       false
     }
   }
@@ -250,9 +362,9 @@ impl GameState {
 
     if let Some(ref cb) = input_cb {
       self.is_processing_input = true;
-      match Platform::read_input() {
+      match self.platform.read_input() {
         Some(input) => {
-          Platform::hide_prompt();
+          self.platform.hide_prompt();
           cb(self, input);
           // Note that at this point, self.input_callback may be
           // set again, if the callback asked for input again.
@@ -275,7 +387,7 @@ impl GameState {
                  *and* run a new input callback simultaneously");
         self.read_input_again = false;
         self.input_callback = input_cb;
-        Platform::show_prompt(self.last_input_prompt.as_str());
+        self.platform.show_prompt(self.last_input_prompt.as_str());
       }
 
       return;
@@ -0,0 +1,260 @@
+use std::fmt;
+use std::str::FromStr;
+
+use game_state::GameState;
+use platform::AbstractPlatform;
+
+pub const HIGH_SCORES_KEY: &str = "highscores";
+
+// Only this many of the best games are remembered.
+pub const MAX_HIGH_SCORES: usize = 10;
+
+// The first line of the high score data. The number at the end should
+// be bumped whenever the format changes.
+const HIGH_SCORES_HEADER: &str = "werewolves-and-wanderer high scores 1";
+
+// The high scores are stored as plain text, one game per line, best
+// first, e.g.:
+//
+//   werewolves-and-wanderer high scores 1
+//   628 3 1 escaped 2017-05-06 Alice Smith
+//   140 20 0 died - Bob
+//
+// The fields are the score, tally, number of monsters killed, how the
+// game ended, and the date it ended on ("-" if unknown). The player's
+// name comes last, since it may contain spaces.
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Outcome {
+  Escaped,
+  Died,
+  Quit,
+}
+
+impl fmt::Display for Outcome {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.pad(match *self {
+      Outcome::Escaped => "escaped",
+      Outcome::Died => "died",
+      Outcome::Quit => "quit",
+    })
+  }
+}
+
+impl FromStr for Outcome {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, String> {
+    match s {
+      "escaped" => Ok(Outcome::Escaped),
+      "died" => Ok(Outcome::Died),
+      "quit" => Ok(Outcome::Quit),
+      _ => Err(format!("Unknown outcome: {}", s)),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HighScore {
+  pub name: String,
+  pub score: i32,
+  pub tally: i32,
+  pub monsters_killed: i32,
+  pub outcome: Outcome,
+  pub date: Option<String>,
+}
+
+impl HighScore {
+  fn parse(line: &str) -> Result<Self, String> {
+    let fields: Vec<&str> = line.splitn(6, ' ').collect();
+    if fields.len() != 6 {
+      return Err(format!("Invalid high score: {:?}", line));
+    }
+    let parse_i32 = |value: &str| value.parse::<i32>().map_err(|_| {
+      format!("Invalid number in high score: {:?}", value)
+    });
+    Ok(Self {
+      score: parse_i32(fields[0])?,
+      tally: parse_i32(fields[1])?,
+      monsters_killed: parse_i32(fields[2])?,
+      outcome: fields[3].parse()?,
+      date: if fields[4] == "-" { None } else {
+        Some(String::from(fields[4]))
+      },
+      name: String::from(fields[5]),
+    })
+  }
+}
+
+impl fmt::Display for HighScore {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {} {} {} {} {}", self.score, self.tally,
+           self.monsters_killed, self.outcome,
+           self.date.as_ref().map_or("-", |date| date.as_str()), self.name)
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct HighScores {
+  pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut lines = data.lines();
+    if lines.next() != Some(HIGH_SCORES_HEADER) {
+      return Err(String::from("This is not a high score table."));
+    }
+    let mut high_scores = Self::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+      high_scores.add(HighScore::parse(line)?);
+    }
+    Ok(high_scores)
+  }
+
+  // Adds the given score to the table, returning its position (starting
+  // at zero) if it was good enough to be kept. Ties go to whoever got
+  // the score first.
+  pub fn add(&mut self, entry: HighScore) -> Option<usize> {
+    let position = self.entries.iter().position(|existing| {
+      entry.score > existing.score
+    }).unwrap_or(self.entries.len());
+    if position >= MAX_HIGH_SCORES {
+      return None;
+    }
+    self.entries.insert(position, entry);
+    self.entries.truncate(MAX_HIGH_SCORES);
+    Some(position)
+  }
+}
+
+impl fmt::Display for HighScores {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{}", HIGH_SCORES_HEADER)?;
+    for entry in self.entries.iter() {
+      writeln!(f, "{}", entry)?;
+    }
+    Ok(())
+  }
+}
+
+impl GameState {
+  // Returns the saved high scores. If they're missing or can't be read,
+  // we just start over with an empty table.
+  fn load_high_scores(&mut self) -> HighScores {
+    self.platform.load_data(HIGH_SCORES_KEY).and_then(|data| {
+      HighScores::parse(&data).ok()
+    }).unwrap_or_default()
+  }
+
+  pub fn record_high_score(&mut self, outcome: Outcome, score: i32) {
+    let entry = HighScore {
+      name: self.player_name.clone(),
+      score,
+      tally: self.tally,
+      monsters_killed: self.monsters_killed,
+      outcome,
+      date: self.platform.today(),
+    };
+    let mut high_scores = self.load_high_scores();
+    let position = high_scores.add(entry);
+    if position.is_some() {
+      let data = high_scores.to_string();
+      let result = self.platform.save_data(HIGH_SCORES_KEY, &data);
+      if let Err(message) = result {
+        wrapln!(self, "Unable to save the high scores: {}", message);
+      }
+    }
+    self.print_high_scores(&high_scores, position);
+  }
+
+  pub fn show_high_scores(&mut self) {
+    let high_scores = self.load_high_scores();
+    self.print_high_scores(&high_scores, None);
+  }
+
+  // Prints the table, marking the entry at the given position, if any.
+  fn print_high_scores(&mut self, high_scores: &HighScores,
+                       highlight: Option<usize>) {
+    if high_scores.entries.is_empty() {
+      wrapln!(self, "Nobody has finished a game yet.\n");
+      return;
+    }
+    wrapln!(self, "High scores:\n");
+    for (i, entry) in high_scores.entries.iter().enumerate() {
+      let marker = if highlight == Some(i) { ">" } else { " " };
+      let date = entry.date.as_ref().map_or("", |date| date.as_str());
+      wrapln!(self, "{}{:>2}. {:<16} {:>5}  {:<7}  {:>3} moves  {:>2} kills  \
+                     {}", marker, i + 1, entry.name, entry.score, entry.outcome,
+              entry.tally, entry.monsters_killed, date);
+    }
+    wrapln!(self);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use platform::scripted::*;
+
+  fn high_score(name: &str, score: i32) -> HighScore {
+    HighScore {
+      name: String::from(name),
+      score,
+      tally: 3,
+      monsters_killed: 1,
+      outcome: Outcome::Escaped,
+      date: Some(String::from("2017-05-06")),
+    }
+  }
+
+  #[test]
+  fn test_high_scores_round_trip_through_text() {
+    let mut high_scores = HighScores::new();
+    high_scores.add(high_score("Alice Smith", 628));
+    high_scores.add(HighScore {
+      outcome: Outcome::Died,
+      date: None,
+      ..high_score("Bob", 140)
+    });
+
+    assert_eq!(HighScores::parse(&high_scores.to_string()),
+               Ok(high_scores));
+    assert!(HighScores::parse("blah").is_err());
+    assert!(HighScores::parse(
+      &format!("{}\n1 2 3 won - Eve", HIGH_SCORES_HEADER)).is_err());
+  }
+
+  #[test]
+  fn test_only_the_best_scores_are_kept() {
+    let mut high_scores = HighScores::new();
+    for i in 0..MAX_HIGH_SCORES {
+      let score = 10 * i as i32;
+      assert_eq!(high_scores.add(high_score("Alice", score)), Some(0));
+    }
+    assert_eq!(high_scores.add(high_score("Bob", 0)), None);
+    assert_eq!(high_scores.add(high_score("Bob", 15)), Some(8));
+    assert_eq!(high_scores.entries.len(), MAX_HIGH_SCORES);
+    assert_eq!(high_scores.entries[9].score, 10);
+  }
+
+  #[test]
+  fn test_finishing_a_game_records_a_high_score() {
+    let (mut state, mut script) = new_empty_game();
+    script.push_inputs(&["Alice", "r", "q"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("Nobody has finished a game yet."));
+    assert!(output.contains(&format!(
+      "> 1. Alice              650  quit       0 moves   0 kills  {}",
+      SCRIPTED_DATE)));
+    let high_scores = HighScores::parse(
+      &script.load_data(HIGH_SCORES_KEY).unwrap()).unwrap();
+    assert_eq!(high_scores.entries[0].outcome, Outcome::Quit);
+  }
+}
@@ -1,113 +1,136 @@
 use game_state::{GameState, GameMode};
-use command::CommandInfo;
-use items::Item;
-use items::Item::*;
-use sized_enum::SizedEnum;
+use command::{CommandInfo, ParsedCommand};
+use items::ItemId;
+use events::GameEvent;
 
 use self::InventoryCommand::*;
 
+// The keys for buying each item in the shop, in catalogue order. '0',
+// 'h' and '?' are left out, since they already mean something here.
+pub const SHOP_KEYS: &str = "123456789abcdefgijklmnopqrstuvwxyz";
+
+// How many of each item in the catalogue the player owns.
 pub struct Inventory {
   quantities: Vec<i32>,
 }
 
 impl Inventory {
-  pub fn new() -> Self {
-    Self { quantities: vec![0; Item::size()] }
+  pub fn new(num_items: usize) -> Self {
+    Self { quantities: vec![0; num_items] }
+  }
+
+  pub fn owns(&self, item: ItemId) -> bool {
+    self.quantities[item.0] > 0
   }
 
-  pub fn owns(&self, item: Item) -> bool {
-    self.quantities[item as usize] > 0
+  pub fn owned<'a>(&'a self) -> impl Iterator<Item = ItemId> + 'a {
+    self.quantities.iter().enumerate()
+      .filter(|&(_, &quantity)| quantity > 0)
+      .map(|(i, _)| ItemId(i))
   }
 
-  pub fn get_quantity(&self, item: Item) -> i32 {
-    self.quantities[item as usize]
+  pub fn get_quantity(&self, item: ItemId) -> i32 {
+    self.quantities[item.0]
   }
 
-  pub fn set_quantity(&mut self, item: Item, amount: i32) {
-    self.quantities[item as usize] = amount;
+  pub fn set_quantity(&mut self, item: ItemId, amount: i32) {
+    self.quantities[item.0] = amount;
   }
 
-  pub fn increase(&mut self, item: Item, amount: i32) {
-    self.quantities[item as usize] += amount;
+  // Free items can be bought in any amount, so this stops at the most
+  // that can be counted.
+  pub fn increase(&mut self, item: ItemId, amount: i32) {
+    self.quantities[item.0] = self.quantities[item.0].saturating_add(amount);
   }
 
-  pub fn decrease(&mut self, item: Item, amount: i32) {
-    self.quantities[item as usize] -= amount;
+  pub fn decrease(&mut self, item: ItemId, amount: i32) {
+    self.quantities[item.0] -= amount;
   }
 
-  pub fn obtain(&mut self, item: Item) {
-    self.quantities[item as usize] = 1;
+  pub fn obtain(&mut self, item: ItemId) {
+    self.quantities[item.0] = 1;
   }
 
-  pub fn lose(&mut self, item: Item) {
-    self.quantities[item as usize] = 0;
+  pub fn lose(&mut self, item: ItemId) {
+    self.quantities[item.0] = 0;
   }
 }
 
 #[derive(Copy, Clone)]
 pub enum InventoryCommand {
-  Buy(Item),
+  Buy(ItemId),
   Leave,
 }
 
-command_processor!(InventoryCommand, {
-  let buy = |item: Item| {
-    format!("buy {} (${}{})",
-            item, item.price(),
-            if item.can_own_many() { " per unit" } else { "" })
-  };
-
-  vec![
-    CommandInfo::new('1', buy(Torch), Buy(Torch)),
-    CommandInfo::new('2', buy(Axe), Buy(Axe)),
-    CommandInfo::new('3', buy(Sword), Buy(Sword)),
-    CommandInfo::new('4', buy(Food), Buy(Food)),
-    CommandInfo::new('5', buy(Amulet), Buy(Amulet)),
-    CommandInfo::new('6', buy(Armor), Buy(Armor)),
-    CommandInfo::new('0', String::from("continue adventure"), Leave),
-  ]
+command_processor!(InventoryCommand, |state| {
+  let catalogue = &state.map.catalogue;
+  let mut commands: Vec<_> = catalogue.ids().zip(SHOP_KEYS.chars())
+    .map(|(id, key)| {
+      let item = catalogue.get(id);
+      let desc = format!("buy {} (${}{})",
+                         item, item.price,
+                         if item.stackable { " per unit" } else { "" });
+      CommandInfo::new(key, desc, Buy(id))
+        .phrases(&[format!("buy {}", item.noun), item.noun.clone()])
+    }).collect();
+  commands.push(CommandInfo::new('0', "continue adventure", Leave)
+                .phrases(&["continue", "leave", "done"]));
+  commands
 });
 
 impl GameState {
-  fn buy_quantity(&mut self, item: Item, quantity: i32) {
-    let price = item.price() * quantity;
-    if self.wealth < price {
-      self.accuse_player_of_cheating();
-    } else {
-      self.wealth -= price;
-      if item.can_own_many() {
-        self.items.increase(item, quantity);
-        wrapln!("You bought {} unit{} of {}.",
-                quantity, if quantity > 1 { "s" } else { "" }, item);
-      } else {
-        self.items.obtain(item);
-        wrapln!("You bought {}.", item);
-      }
-      self.print_wealth();
+  fn buy_quantity(&mut self, id: ItemId, quantity: i32) {
+    let item = self.map.catalogue.get(id).clone();
+    // A price too big to even count is certainly more than the player
+    // has.
+    match item.price.checked_mul(quantity) {
+      Some(price) if price <= self.wealth => {
+        self.wealth -= price;
+        if item.stackable {
+          self.items.increase(id, quantity);
+          wrapln!(self, "You bought {} unit{} of {}.",
+                  quantity, if quantity > 1 { "s" } else { "" }, item);
+        } else {
+          self.items.obtain(id);
+          wrapln!(self, "You bought {}.", item);
+        }
+        self.print_wealth();
+        self.emit(GameEvent::ItemBought(id, quantity));
+      },
+      _ => { self.accuse_player_of_cheating() },
     }
   }
 
-  fn buy(&mut self, item: Item) {
-    if item.can_own_many() {
+  fn buy(&mut self, id: ItemId, amount: Option<i32>) {
+    let stackable = self.map.catalogue.get(id).stackable;
+    if let (true, Some(amount)) = (stackable, amount) {
+      if amount <= 0 {
+        wrapln!(self, "Fine, don't buy any then.");
+      } else {
+        self.buy_quantity(id, amount);
+      }
+    } else if stackable {
       self.ask_i32("How many units? ", move |state, amount| {
         if amount <= 0 {
-          wrapln!("Fine, don't buy any then.");
+          wrapln!(state, "Fine, don't buy any then.");
         } else {
-          state.buy_quantity(item, amount);
+          state.buy_quantity(id, amount);
         }
       });
     } else {
-      self.buy_quantity(item, 1);
+      self.buy_quantity(id, 1);
     }
   }
 
-  fn process_inventory_cmd(&mut self, cmd: InventoryCommand) {
-    match cmd {
-      Buy(item) => {
-        if !item.can_own_many() && self.items.owns(item) {
-          wrapln!("You already own {}.\n", item);
+  fn process_inventory_cmd(&mut self,
+                           parsed: ParsedCommand<InventoryCommand>) {
+    match parsed.cmd {
+      Buy(id) => {
+        if !self.map.catalogue.get(id).stackable && self.items.owns(id) {
+          let name = self.map.catalogue.get(id).name.clone();
+          wrapln!(self, "You already own {}.\n", name);
         } else {
-          self.buy(item);
+          self.buy(id, parsed.amount);
         }
       },
       Leave => { self.set_mode(GameMode::Primary) },
@@ -116,18 +139,113 @@ impl GameState {
 
   pub fn tick_inventory_mode(&mut self) {
     if self.show_desc {
-      wrapln!("Provisions & inventory\n");
+      wrapln!(self, "Provisions & inventory\n");
       self.print_wealth();
-      wrapln!();
-      InventoryCommand::show_help();
-      wrapln!();
+      wrapln!(self);
+      InventoryCommand::show_help(self);
+      wrapln!(self);
       self.show_desc = false;
     }
 
     self.ask("What do you want to buy? ", |state, input| {
-      if let Some(cmd) = InventoryCommand::get_from_input(input) {
-        state.process_inventory_cmd(cmd);
+      if let Some(parsed) = InventoryCommand::get_from_input(state, input) {
+        state.process_inventory_cmd(parsed);
       }
     });
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::Inventory;
+  use items::Catalogue;
+  use game_state::GameMode;
+  use command::ParserMode;
+  use platform::scripted::*;
+  use achievements::Fact;
+
+  #[test]
+  fn test_buying_items() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "i", "1", "4", "3", "0"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    let torch = state.map.catalogue.find("torch").unwrap();
+    let food = state.map.catalogue.find("food").unwrap();
+    assert!(output.contains("You bought a flaming torch."));
+    assert!(output.contains("You bought 3 units of food."));
+    assert!(state.items.owns(torch));
+    assert_eq!(state.items.get_quantity(food), 3);
+    assert_eq!(state.wealth, 48);
+    assert!(state.achievements.knows(Fact::BoughtTorch));
+    assert_eq!(state.curr_mode, GameMode::Primary);
+  }
+
+  #[test]
+  fn test_buying_items_by_name() {
+    let (mut state, script) = new_empty_game();
+    state.parser = ParserMode::Words;
+    script.push_inputs(&["Alice", "shop", "buy sword",
+                          "buy 3 food", "done"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("  3, buy sword - buy a sword ($20)"));
+    let sword = state.map.catalogue.find("sword").unwrap();
+    let food = state.map.catalogue.find("food").unwrap();
+    assert!(state.items.owns(sword));
+    assert_eq!(state.items.get_quantity(food), 3);
+    assert_eq!(state.wealth, 43);
+    assert_eq!(state.curr_mode, GameMode::Primary);
+  }
+
+  #[test]
+  fn test_buying_too_much_is_cheating() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "i", "1", "4", "100"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("YOU HAVE TRIED TO CHEAT ME!"));
+    assert_eq!(state.wealth, 0);
+    assert!(!state.items.owns(state.map.catalogue.find("torch").unwrap()));
+  }
+
+  #[test]
+  fn test_buying_more_than_can_be_counted_is_cheating() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "i", "4", "2000000000"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("YOU HAVE TRIED TO CHEAT ME!"));
+    assert_eq!(state.wealth, 0);
+    assert_eq!(state.food(), 0);
+  }
+
+  #[test]
+  fn test_shop_sells_whatever_the_catalogue_lists() {
+    let (mut state, script) = new_empty_game();
+    state.map.catalogue = Catalogue::parse("
+item lantern
+name a brass lantern
+price 25
+light
+item shield
+name a wooden shield
+price 30
+armor 4/5
+").unwrap();
+    state.items = Inventory::new(state.map.catalogue.len());
+    script.push_inputs(&["Alice", "i", "1", "2", "0", "e", "l"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("  1 - buy a brass lantern ($25)"));
+    assert!(output.contains("  2 - buy a wooden shield ($30)"));
+    assert!(output.contains("You are in the hallway."));
+    assert!(output.contains("You are wearing a wooden shield."));
+    assert_eq!(state.wealth, 20);
+  }
+}
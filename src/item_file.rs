@@ -0,0 +1,249 @@
+use map_file::logical_lines;
+use items::{Catalogue, Item, ItemId, ItemUse};
+use inventory::SHOP_KEYS;
+use combat_engine::Modifier;
+
+// Items are described in the same line-based text format as maps,
+// e.g.:
+//
+//   item lantern
+//   name a brass lantern
+//   price 25
+//   light
+//
+//   item shield
+//   name a wooden shield
+//   price 30
+//   armor 4/5
+//
+// The directives are:
+//
+//   item <key>            - start describing a new item
+//   name <text>           - what the shop calls it (defaults to the key)
+//   noun <word>           - what the player calls it (defaults to the
+//                           key)
+//   price <number>        - how much it costs (defaults to 0)
+//   stackable             - the player can own many units of it
+//   light                 - it lets the player see in the dark
+//   weapon <num>/<den>    - fighting with it multiplies the danger
+//                           level by num/den
+//   armor <num>/<den>     - wearing it multiplies the danger level by
+//                           num/den
+//   use food <strength>   - it can be eaten, restoring this much
+//                           strength per unit
+//   use teleport          - it can be invoked to teleport the player
+//
+// Item keys can't contain whitespace. At most one item can be food and
+// at most one can teleport.
+
+struct ItemParser {
+  catalogue: Catalogue,
+  curr_item: Option<ItemId>,
+}
+
+fn parse_number<T: ::std::str::FromStr>(directive: &str,
+                                        value: &str) -> Result<T, String> {
+  value.parse::<T>().map_err(|_| {
+    format!("Invalid number for '{}': {}", directive, value)
+  })
+}
+
+fn parse_modifier(directive: &str, value: &str) -> Result<Modifier, String> {
+  let invalid = || format!("Invalid fraction for '{}': {}", directive, value);
+  let slash = value.find('/').ok_or_else(invalid)?;
+  let num = parse_number(directive, &value[..slash])?;
+  let den = parse_number(directive, &value[slash + 1..])?;
+  if num <= 0 || den <= 0 {
+    return Err(invalid());
+  }
+  Ok(Modifier::new(num, den))
+}
+
+impl ItemParser {
+  fn curr_item(&mut self, directive: &str) -> Result<&mut Item, String> {
+    match self.curr_item {
+      Some(id) => Ok(self.catalogue.get_mut(id)),
+      None => Err(format!("'{}' must come after an 'item' directive",
+                          directive)),
+    }
+  }
+
+  fn parse_directive(&mut self, directive: &str,
+                     rest: &str) -> Result<(), String> {
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let expect_args = |n: usize| {
+      if args.len() == n {
+        Ok(())
+      } else {
+        Err(format!("'{}' expects {} argument{}", directive, n,
+                    if n == 1 { "" } else { "s" }))
+      }
+    };
+
+    match directive {
+      "item" => {
+        expect_args(1)?;
+        if self.catalogue.find(args[0]).is_some() {
+          return Err(format!("Item {} is declared twice", args[0]));
+        }
+        self.curr_item = Some(self.catalogue.add(Item::new(args[0])));
+      },
+      "name" => {
+        self.curr_item(directive)?.name = String::from(rest);
+      },
+      "noun" => {
+        expect_args(1)?;
+        self.curr_item(directive)?.noun = String::from(rest);
+      },
+      "price" => {
+        expect_args(1)?;
+        let price = parse_number(directive, args[0])?;
+        if price < 0 {
+          return Err(format!("Invalid number for '{}': {}", directive,
+                             args[0]));
+        }
+        self.curr_item(directive)?.price = price;
+      },
+      "stackable" => {
+        expect_args(0)?;
+        self.curr_item(directive)?.stackable = true;
+      },
+      "light" => {
+        expect_args(0)?;
+        self.curr_item(directive)?.light = true;
+      },
+      "weapon" => {
+        expect_args(1)?;
+        let modifier = parse_modifier(directive, args[0])?;
+        self.curr_item(directive)?.weapon = Some(modifier);
+      },
+      "armor" => {
+        expect_args(1)?;
+        let modifier = parse_modifier(directive, args[0])?;
+        self.curr_item(directive)?.armor = Some(modifier);
+      },
+      "use" => {
+        let special = match args.first() {
+          Some(&"food") => {
+            expect_args(2)?;
+            ItemUse::Food(parse_number(directive, args[1])?)
+          },
+          Some(&"teleport") => {
+            expect_args(1)?;
+            ItemUse::Teleport
+          },
+          _ => { return Err(format!("Unknown use: {}", rest)); }
+        };
+        self.curr_item(directive)?.special = Some(special);
+      },
+      _ => { return Err(format!("Unknown directive: {}", directive)); }
+    }
+    Ok(())
+  }
+}
+
+impl Catalogue {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut parser = ItemParser {
+      catalogue: Catalogue::new(),
+      curr_item: None,
+    };
+
+    for (line_number, line) in logical_lines(data) {
+      let (directive, rest) = match line.find(' ') {
+        Some(i) => (&line[..i], line[i + 1..].trim()),
+        None => (line.as_str(), ""),
+      };
+      parser.parse_directive(directive, rest).map_err(|message| {
+        format!("Line {}: {}", line_number, message)
+      })?;
+    }
+
+    let catalogue = parser.catalogue;
+    if catalogue.is_empty() {
+      return Err(String::from("There are no items."));
+    }
+    if catalogue.len() > SHOP_KEYS.len() {
+      return Err(format!("There can be at most {} items.", SHOP_KEYS.len()));
+    }
+    let count = |special: fn(&Item) -> bool| {
+      catalogue.ids().filter(|&id| special(catalogue.get(id))).count()
+    };
+    if count(Item::is_food) > 1 {
+      return Err(String::from("Only one item can be food."));
+    }
+    if count(|item| item.special == Some(ItemUse::Teleport)) > 1 {
+      return Err(String::from("Only one item can teleport."));
+    }
+
+    Ok(catalogue)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use items::{Catalogue, ItemId, ItemUse};
+  use combat_engine::Modifier;
+
+  const TINY_CATALOGUE: &str = "
+    item lantern
+    name a brass
+      lantern
+    price 25
+    light
+    item shield
+    name a wooden shield
+    price 30
+    armor 4/5
+    item potion
+    noun flask
+    price 5
+    stackable
+    use food 25
+  ";
+
+  #[test]
+  fn test_parse_tiny_catalogue() {
+    let data = TINY_CATALOGUE.replace("\n    ", "\n");
+    let catalogue = Catalogue::parse(&data).unwrap();
+    assert_eq!(catalogue.len(), 3);
+    assert_eq!(catalogue.food(), Some(ItemId(2)));
+    assert_eq!(catalogue.teleporter(), None);
+
+    let lantern = catalogue.get(ItemId(0));
+    assert_eq!(lantern.to_string(), "a brass lantern");
+    assert_eq!(lantern.noun, "lantern");
+    assert!(lantern.light && !lantern.affects_combat());
+
+    let shield = catalogue.get(catalogue.find("shield").unwrap());
+    assert_eq!(shield.armor, Some(Modifier::new(4, 5)));
+    assert_eq!(shield.price, 30);
+
+    let potion = catalogue.get(ItemId(2));
+    assert_eq!(potion.name, "potion");
+    assert_eq!(potion.noun, "flask");
+    assert!(potion.stackable);
+    assert_eq!(potion.special, Some(ItemUse::Food(25)));
+
+    assert_eq!(Catalogue::parse(&data.replace("4/5", "4")).err(),
+               Some(String::from("Line 10: Invalid fraction for 'armor': 4")));
+    assert_eq!(Catalogue::parse(&data.replace("light", "use food 1")).err(),
+               Some(String::from("Only one item can be food.")));
+    assert_eq!(Catalogue::parse("").err(),
+               Some(String::from("There are no items.")));
+    assert_eq!(Catalogue::parse(&data.replace("price 5", "price -5")).err(),
+               Some(String::from("Line 13: Invalid number for 'price': -5")));
+    assert!(Catalogue::parse("price 5").is_err());
+  }
+
+  #[test]
+  fn test_classic_items_are_valid() {
+    let catalogue = Catalogue::classic();
+    assert_eq!(catalogue.len(), 6);
+    let torch = catalogue.find("torch").unwrap();
+    assert_eq!(catalogue.get(torch).name, "a flaming torch");
+    assert!(catalogue.get(torch).light);
+    assert_eq!(catalogue.food(), catalogue.find("food"));
+    assert_eq!(catalogue.teleporter(), catalogue.find("amulet"));
+  }
+}
@@ -1,57 +1,150 @@
 use std::fmt;
 
-use sized_enum::SizedEnum;
+use combat_engine::Modifier;
 
-use self::Item::*;
+// The items from the book, which are sold unless the player asks for
+// different ones. See `item_file` for details on the format.
+const CLASSIC_ITEMS: &str = include_str!("../maps/items.txt");
 
-const NUM_ITEMS: usize = 6;
+// Items are identified by the order in which they were added to the
+// catalogue.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ItemId(pub usize);
 
-enum_from_primitive! {
-#[derive(PartialEq, Copy, Clone)]
-pub enum Item {
-  Torch,
-  Axe,
-  Sword,
-  Food,
-  Amulet,
-  Armor,
-}
+// What an item can be used for, besides fighting and seeing.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ItemUse {
+  // The item can be eaten, restoring this much strength per unit.
+  Food(i32),
+
+  // The item whisks the player away to a random room.
+  Teleport,
 }
 
-impl SizedEnum for Item {
-  fn size() -> usize { NUM_ITEMS }
+#[derive(Debug, PartialEq, Clone)]
+pub struct Item {
+  pub key: String,
+
+  // What the item is called in the shop, e.g. "a flaming torch".
+  pub name: String,
+
+  // The word the player can use to refer to the item.
+  pub noun: String,
+
+  pub price: i32,
+
+  // Whether the player can own many units of the item, rather than
+  // just one.
+  pub stackable: bool,
+
+  // Whether the item lets the player see in the dark.
+  pub light: bool,
+
+  // How the item changes the danger level of fights. Weapons are only
+  // used when the player has one, while armor is always worn.
+  pub weapon: Option<Modifier>,
+  pub armor: Option<Modifier>,
+
+  pub special: Option<ItemUse>,
 }
 
 impl Item {
-  pub fn price(&self) -> i32 {
-    match *self {
-      Torch => 15,
-      Axe => 10,
-      Sword => 20,
-      Food => 4,
-      Amulet => 30,
-      Armor => 50,
+  pub fn new(key: &str) -> Self {
+    Self {
+      key: String::from(key),
+      name: String::from(key),
+      noun: String::from(key),
+      price: 0,
+      stackable: false,
+      light: false,
+      weapon: None,
+      armor: None,
+      special: None,
     }
   }
 
-  pub fn can_own_many(&self) -> bool {
-    *self == Food
+  pub fn is_food(&self) -> bool {
+    matches!(self.special, Some(ItemUse::Food(_)))
   }
 
-  pub fn as_str(&self) -> &'static str {
-    match *self {
-      Torch => "a flaming torch",
-      Axe => "an axe",
-      Sword => "a sword",
-      Food => "food",
-      Amulet => "the magic amulet",
-      Armor => "a suit of armor",
+  // How much strength eating a unit of the item restores.
+  pub fn nourishment(&self) -> i32 {
+    match self.special {
+      Some(ItemUse::Food(strength)) => strength,
+      _ => 0,
     }
   }
+
+  pub fn affects_combat(&self) -> bool {
+    self.weapon.is_some() || self.armor.is_some()
+  }
 }
 
 impl fmt::Display for Item {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.as_str())
+    write!(f, "{}", self.name)
+  }
+}
+
+// All the kinds of item that the shop sells.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Catalogue {
+  items: Vec<Item>,
+}
+
+impl Catalogue {
+  pub fn new() -> Self {
+    Self { items: Vec::new() }
+  }
+
+  pub fn classic() -> Self {
+    Self::parse(CLASSIC_ITEMS).expect("The built-in items are invalid")
+  }
+
+  pub fn add(&mut self, item: Item) -> ItemId {
+    self.items.push(item);
+    ItemId(self.items.len() - 1)
+  }
+
+  pub fn get(&self, id: ItemId) -> &Item {
+    &self.items[id.0]
+  }
+
+  pub fn get_mut(&mut self, id: ItemId) -> &mut Item {
+    &mut self.items[id.0]
+  }
+
+  pub fn len(&self) -> usize {
+    self.items.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.items.is_empty()
+  }
+
+  pub fn ids(&self) -> impl Iterator<Item = ItemId> {
+    (0..self.items.len()).map(ItemId)
+  }
+
+  pub fn find(&self, key: &str) -> Option<ItemId> {
+    self.items.iter().position(|item| item.key == key).map(ItemId)
+  }
+
+  // The item the player eats to regain strength, if there is one.
+  pub fn food(&self) -> Option<ItemId> {
+    self.items.iter().position(Item::is_food).map(ItemId)
+  }
+
+  // The item the player invokes to teleport, if there is one.
+  pub fn teleporter(&self) -> Option<ItemId> {
+    self.items.iter().position(|item| {
+      item.special == Some(ItemUse::Teleport)
+    }).map(ItemId)
+  }
+}
+
+impl Default for Catalogue {
+  fn default() -> Self {
+    Self::new()
   }
 }
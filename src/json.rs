@@ -0,0 +1,320 @@
+use std::fmt;
+
+// Just enough JSON to talk to other programs. Numbers are always
+// whole, since that's all the game ever deals with.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(i64),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>),
+}
+
+impl Json {
+  pub fn string<S: AsRef<str>>(s: S) -> Self {
+    Json::String(String::from(s.as_ref()))
+  }
+
+  // Builds an object from the given fields, keeping them in order.
+  pub fn object(fields: Vec<(&str, Json)>) -> Self {
+    Json::Object(fields.into_iter().map(|(key, value)| {
+      (String::from(key), value)
+    }).collect())
+  }
+
+  pub fn get(&self, key: &str) -> Option<&Json> {
+    match *self {
+      Json::Object(ref fields) => {
+        fields.iter().find(|field| field.0 == key).map(|field| &field.1)
+      },
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match *self {
+      Json::String(ref s) => Some(s.as_str()),
+      _ => None,
+    }
+  }
+
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let mut parser = Parser {
+      chars: text.chars().collect(),
+      pos: 0,
+      depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+      return Err(parser.error("Unexpected text after JSON value"));
+    }
+    Ok(value)
+  }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+  write!(f, "\"")?;
+  for c in s.chars() {
+    match c {
+      '"' => write!(f, "\\\"")?,
+      '\\' => write!(f, "\\\\")?,
+      '\n' => write!(f, "\\n")?,
+      '\r' => write!(f, "\\r")?,
+      '\t' => write!(f, "\\t")?,
+      c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+      c => write!(f, "{}", c)?,
+    }
+  }
+  write!(f, "\"")
+}
+
+// Formats the value on a single line, so it can be used in
+// line-delimited protocols.
+impl fmt::Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Json::Null => write!(f, "null"),
+      Json::Bool(b) => write!(f, "{}", b),
+      Json::Number(n) => write!(f, "{}", n),
+      Json::String(ref s) => write_string(f, s),
+      Json::Array(ref values) => {
+        write!(f, "[")?;
+        for (i, value) in values.iter().enumerate() {
+          if i > 0 { write!(f, ",")?; }
+          write!(f, "{}", value)?;
+        }
+        write!(f, "]")
+      },
+      Json::Object(ref fields) => {
+        write!(f, "{{")?;
+        for (i, (key, value)) in fields.iter().enumerate() {
+          if i > 0 { write!(f, ",")?; }
+          write_string(f, key)?;
+          write!(f, ":{}", value)?;
+        }
+        write!(f, "}}")
+      },
+    }
+  }
+}
+
+// How many arrays and objects can sit inside each other. Nothing the
+// game exchanges comes close, and going deeper could overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+  chars: Vec<char>,
+  pos: usize,
+  depth: usize,
+}
+
+impl Parser {
+  fn error(&self, message: &str) -> String {
+    format!("{} at character {}", message, self.pos + 1)
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).cloned()
+  }
+
+  fn next(&mut self) -> Option<char> {
+    let c = self.peek();
+    if c.is_some() {
+      self.pos += 1;
+    }
+    c
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(c) = self.peek() {
+      if !c.is_whitespace() { break; }
+      self.pos += 1;
+    }
+  }
+
+  fn expect(&mut self, expected: char) -> Result<(), String> {
+    self.skip_whitespace();
+    if self.next() == Some(expected) {
+      Ok(())
+    } else {
+      Err(self.error(&format!("Expected '{}'", expected)))
+    }
+  }
+
+  fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+    for expected in word.chars() {
+      if self.next() != Some(expected) {
+        return Err(self.error("Invalid literal"));
+      }
+    }
+    Ok(value)
+  }
+
+  fn value(&mut self) -> Result<Json, String> {
+    self.skip_whitespace();
+    match self.peek() {
+      Some('n') => self.literal("null", Json::Null),
+      Some('t') => self.literal("true", Json::Bool(true)),
+      Some('f') => self.literal("false", Json::Bool(false)),
+      Some('"') => Ok(Json::String(self.string()?)),
+      Some('[') => self.nested(Parser::array),
+      Some('{') => self.nested(Parser::object),
+      Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+      Some(_) => Err(self.error("Unexpected character")),
+      None => Err(self.error("Unexpected end of JSON")),
+    }
+  }
+
+  fn nested<F>(&mut self, parse: F) -> Result<Json, String>
+    where F: FnOnce(&mut Self) -> Result<Json, String>
+  {
+    if self.depth == MAX_DEPTH {
+      return Err(self.error("JSON is nested too deeply"));
+    }
+    self.depth += 1;
+    let value = parse(self);
+    self.depth -= 1;
+    value
+  }
+
+  fn number(&mut self) -> Result<Json, String> {
+    let start = self.pos;
+    if self.peek() == Some('-') {
+      self.pos += 1;
+    }
+    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+      self.pos += 1;
+    }
+    if let Some('.') | Some('e') | Some('E') = self.peek() {
+      return Err(self.error("Only whole numbers are supported"));
+    }
+    let digits: String = self.chars[start..self.pos].iter().collect();
+    digits.parse::<i64>().map(Json::Number).map_err(|_| {
+      self.error("Invalid number")
+    })
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    self.expect('"')?;
+    let mut s = String::new();
+    loop {
+      match self.next() {
+        Some('"') => return Ok(s),
+        Some('\\') => {
+          let c = match self.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => self.unicode_escape()?,
+            _ => return Err(self.error("Invalid escape")),
+          };
+          s.push(c);
+        },
+        Some(c) => s.push(c),
+        None => return Err(self.error("Unterminated string")),
+      }
+    }
+  }
+
+  // Characters outside the Basic Multilingual Plane aren't supported,
+  // since players have no reason to type them.
+  fn unicode_escape(&mut self) -> Result<char, String> {
+    if self.pos + 4 > self.chars.len() {
+      return Err(self.error("Invalid unicode escape"));
+    }
+    let hex: String = self.chars[self.pos..self.pos + 4].iter().collect();
+    self.pos += 4;
+    u32::from_str_radix(&hex, 16).ok().and_then(::std::char::from_u32)
+      .ok_or_else(|| self.error("Invalid unicode escape"))
+  }
+
+  fn array(&mut self) -> Result<Json, String> {
+    self.expect('[')?;
+    let mut values = Vec::new();
+    self.skip_whitespace();
+    if self.peek() == Some(']') {
+      self.pos += 1;
+      return Ok(Json::Array(values));
+    }
+    loop {
+      values.push(self.value()?);
+      self.skip_whitespace();
+      match self.next() {
+        Some(',') => {},
+        Some(']') => return Ok(Json::Array(values)),
+        _ => return Err(self.error("Expected ',' or ']'")),
+      }
+    }
+  }
+
+  fn object(&mut self) -> Result<Json, String> {
+    self.expect('{')?;
+    let mut fields = Vec::new();
+    self.skip_whitespace();
+    if self.peek() == Some('}') {
+      self.pos += 1;
+      return Ok(Json::Object(fields));
+    }
+    loop {
+      self.skip_whitespace();
+      let key = self.string()?;
+      self.expect(':')?;
+      fields.push((key, self.value()?));
+      self.skip_whitespace();
+      match self.next() {
+        Some(',') => {},
+        Some('}') => return Ok(Json::Object(fields)),
+        _ => return Err(self.error("Expected ',' or '}'")),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_json_round_trips_through_text() {
+    let value = Json::object(vec![
+      ("name", Json::string("Alice \"the\" Brave\n")),
+      ("strength", Json::Number(-5)),
+      ("items", Json::Array(vec![Json::Bool(true), Json::Null])),
+      ("empty", Json::object(vec![])),
+    ]);
+    let text = value.to_string();
+
+    assert_eq!(text, "{\"name\":\"Alice \\\"the\\\" Brave\\n\",\
+                      \"strength\":-5,\"items\":[true,null],\"empty\":{}}");
+    assert_eq!(Json::parse(&text), Ok(value));
+  }
+
+  #[test]
+  fn test_parsing_json() {
+    let value = Json::parse(" { \"input\" : \"\\u0041b\" , \"x\": [ ] } ");
+    assert_eq!(value.unwrap().get("input").and_then(|v| v.as_str()),
+               Some("Ab"));
+    assert!(Json::parse("{\"input\": 1.5}").is_err());
+    assert!(Json::parse("{\"input\" \"n\"}").is_err());
+    assert!(Json::parse("[1, 2").is_err());
+    assert!(Json::parse("nul").is_err());
+    assert!(Json::parse("{} {}").is_err());
+  }
+
+  #[test]
+  fn test_deeply_nested_json_is_rejected() {
+    let ok = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+    assert!(Json::parse(&ok).is_ok());
+    assert_eq!(Json::parse(&"[".repeat(100000)),
+               Err(format!("JSON is nested too deeply at character {}",
+                           MAX_DEPTH + 1)));
+  }
+}
@@ -0,0 +1,246 @@
+use std::io::{self, BufRead, Write};
+
+use json::Json;
+use map::{Map, RoomContents};
+use game_state::{GameState, GameMode};
+use command::{CommandInfo, CommandProcessor, ParserMode};
+use primary_mode::PrimaryCommand;
+use inventory::InventoryCommand;
+use combat::{FleeCommand, FLEE_PROMPT};
+use platform::ProcessPlatform;
+use platform::scripted::{ScriptedPlatform, TranscriptEntry};
+
+// Lets another program drive the game by exchanging one JSON object per
+// line. Whenever the game wants input, it sends a message like:
+//
+//   {"mode":"primary","prompt":"What do you want to do? ",
+//    "output":["Alice, your strength is 100.", ...],
+//    "room":{"name":"Entrance","dark":false,"treasure":null,
+//            "monster":null},
+//    "player":{"name":"Alice","strength":100,"wealth":75,"food":0,
+//              "tally":0,"monsters_killed":0,"items":["torch"]},
+//    "commands":[{"key":"n","description":"go north",
+//                 "phrases":["go north","north"]}, ...],
+//    "finished":false}
+//
+// (It's all on one line in practice.) The output contains every line
+// the game printed since the last message. The commands are the ones
+// that make sense in the current mode, including which way to run when
+// fleeing a fight. Prompts that want free text, like the player's name
+// or how many of something to buy, send an empty list, as do yes-or-no
+// questions. When the player can't see, the room's name and contents
+// are null.
+//
+// The other program replies with e.g. `{"input":"n"}`, which is treated
+// exactly as though the player had typed it. If the reply can't be
+// understood, the game sends back `{"error":"..."}` and waits for
+// another one.
+//
+// The game runs headless, so anything it saves only lasts as long as
+// the connection.
+
+fn commands_json<T: Copy>(infos: Vec<CommandInfo<T>>) -> Json {
+  Json::Array(infos.into_iter().filter(|info| !info.hidden).map(|info| {
+    Json::object(vec![
+      ("key", Json::String(info.key.to_string())),
+      ("description", Json::String(info.desc)),
+      ("phrases", Json::Array(info.phrases.into_iter()
+                              .map(Json::String).collect())),
+    ])
+  }).collect())
+}
+
+fn room_json(state: &GameState) -> Json {
+  if !state.can_player_see() {
+    return Json::object(vec![
+      ("name", Json::Null),
+      ("dark", Json::Bool(true)),
+      ("treasure", Json::Null),
+      ("monster", Json::Null),
+    ]);
+  }
+  let room = state.map.room(state.curr_room);
+  let (treasure, monster) = match room.contents {
+    Some(RoomContents::Treasure(amount)) => {
+      (Json::Number(amount as i64), Json::Null)
+    },
+    Some(RoomContents::Terror(monster_id)) => {
+      (Json::Null, Json::string(&state.map.monsters.get(monster_id).name))
+    },
+    None => (Json::Null, Json::Null),
+  };
+  Json::object(vec![
+    ("name", Json::string(&room.name)),
+    ("dark", Json::Bool(false)),
+    ("treasure", treasure),
+    ("monster", monster),
+  ])
+}
+
+fn player_json(state: &GameState) -> Json {
+  let items = state.items.owned().map(|id| {
+    state.map.catalogue.get(id)
+  }).filter(|item| !item.stackable).map(|item| {
+    Json::string(&item.noun)
+  }).collect();
+  Json::object(vec![
+    ("name", Json::string(&state.player_name)),
+    ("strength", Json::Number(state.strength as i64)),
+    ("wealth", Json::Number(state.wealth as i64)),
+    ("food", Json::Number(state.food() as i64)),
+    ("tally", Json::Number(state.tally as i64)),
+    ("monsters_killed", Json::Number(state.monsters_killed as i64)),
+    ("items", Json::Array(items)),
+  ])
+}
+
+// Describes the game as it is now, along with everything in the
+// transcript since the last message.
+fn message_json(state: &GameState, transcript: Vec<TranscriptEntry>) -> Json {
+  let mut output = Vec::new();
+  let mut prompt = String::new();
+  for entry in transcript {
+    match entry {
+      TranscriptEntry::Line(text) => {
+        output.extend(text.split('\n').map(Json::string));
+      },
+      TranscriptEntry::Prompt(text) => { prompt = text },
+      _ => {},
+    }
+  }
+  let commands = match state.curr_mode {
+    GameMode::Primary => {
+      commands_json(PrimaryCommand::get_command_info(state))
+    },
+    GameMode::Inventory => {
+      commands_json(InventoryCommand::get_command_info(state))
+    },
+    // The rest of a fight is yes-or-no questions and pressing enter.
+    GameMode::Combat(_) if state.prompt() == FLEE_PROMPT => {
+      commands_json(FleeCommand::get_command_info(state))
+    },
+    _ => Json::Array(Vec::new()),
+  };
+  Json::object(vec![
+    ("mode", Json::string(state.curr_mode.name())),
+    ("prompt", Json::String(prompt)),
+    ("output", Json::Array(output)),
+    ("room", room_json(state)),
+    ("player", player_json(state)),
+    ("commands", commands),
+    ("finished", Json::Bool(state.is_finished())),
+  ])
+}
+
+// Surrounding whitespace is dropped, just as when the player types at
+// the terminal.
+fn parse_reply(line: &str) -> Result<String, String> {
+  let reply = Json::parse(line)?;
+  match reply.get("input").and_then(|input| input.as_str()) {
+    Some(input) => Ok(String::from(input.trim())),
+    None => Err(String::from("Expected an object with an \"input\" string")),
+  }
+}
+
+// Plays a whole game over the given input and output, returning once
+// the game is finished or the input runs out.
+pub fn play<R: BufRead, W: Write>(seed: u64, map: Map, parser: ParserMode,
+                                  input: R, mut output: W) -> io::Result<()> {
+  let script = ScriptedPlatform::new();
+  let mut state = GameState::new(seed, map, Box::new(script.clone()));
+  state.parser = parser;
+  state.map.populate(&mut state.rng);
+
+  let mut lines = input.lines();
+  loop {
+    script.run_until_blocked(&mut state);
+    let message = message_json(&state, script.take_transcript());
+    writeln!(output, "{}", message)?;
+    output.flush()?;
+    if state.is_finished() {
+      return Ok(());
+    }
+
+    loop {
+      let line = match lines.next() {
+        Some(line) => line?,
+        None => return Ok(()),
+      };
+      match parse_reply(&line) {
+        Ok(input) => {
+          script.push_input(input);
+          break;
+        },
+        Err(message) => {
+          let error = Json::object(vec![("error", Json::String(message))]);
+          writeln!(output, "{}", error)?;
+          output.flush()?;
+        },
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn play_lines(replies: &[&str]) -> Vec<Json> {
+    let input = replies.join("\n");
+    let mut output = Vec::new();
+    play(1, Map::castle(), ParserMode::Classic, input.as_bytes(),
+         &mut output).unwrap();
+    String::from_utf8(output).unwrap().lines().map(|line| {
+      Json::parse(line).unwrap()
+    }).collect()
+  }
+
+  #[test]
+  fn test_playing_over_json() {
+    let messages = play_lines(&[
+      "{\"input\": \"Alice\"}",
+      "blah",
+      "{\"input\": \"q\"}",
+    ]);
+
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0].get("mode"), Some(&Json::string("ask_name")));
+    assert_eq!(messages[0].get("prompt"),
+               Some(&Json::string("What is your name, explorer? ")));
+    assert_eq!(messages[0].get("commands"), Some(&Json::Array(vec![])));
+
+    let primary = &messages[1];
+    assert_eq!(primary.get("mode"), Some(&Json::string("primary")));
+    let room = primary.get("room").unwrap();
+    assert_eq!(room.get("name"), Some(&Json::string("Entrance")));
+    let player = primary.get("player").unwrap();
+    assert_eq!(player.get("strength"), Some(&Json::Number(100)));
+    match primary.get("commands") {
+      Some(Json::Array(commands)) => {
+        assert!(commands.contains(&Json::object(vec![
+          ("key", Json::string("q")),
+          ("description", Json::string("quit")),
+          ("phrases", Json::Array(vec![Json::string("quit")])),
+        ])));
+      },
+      other => panic!("Expected commands, got {:?}", other),
+    }
+
+    assert!(messages[2].get("error").is_some());
+
+    let last = &messages[3];
+    assert_eq!(last.get("finished"), Some(&Json::Bool(true)));
+    match last.get("output") {
+      Some(Json::Array(output)) => {
+        assert_eq!(output.last(), Some(&Json::string("Farewell.")));
+      },
+      other => panic!("Expected output, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_replies_are_trimmed() {
+    assert_eq!(parse_reply("{\"input\": \"  n \\n\"}"),
+               Ok(String::from("n")));
+  }
+}
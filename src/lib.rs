@@ -1,16 +1,39 @@
-#[macro_use] extern crate enum_primitive;
+extern crate sized_enum_derive;
 
 #[macro_use] pub mod platform;
 pub mod direction;
 #[macro_use] pub mod command;
+pub mod word_parser;
 pub mod map;
+pub mod map_file;
+pub mod map_gen;
+pub mod map_validation;
+pub mod map_options;
+pub mod automap;
+pub mod events;
+pub mod achievements;
 pub mod game_state;
 pub mod primary_mode;
 pub mod combat;
+pub mod doors;
+pub mod triggers;
+pub mod wandering;
+pub mod combat_engine;
+pub mod combat_sim;
 pub mod items;
+pub mod item_file;
 pub mod monsters;
+pub mod monster_file;
 pub mod inventory;
 pub mod util;
+pub mod rng;
+pub mod save;
+pub mod high_scores;
+pub mod score;
+pub mod session;
+pub mod json;
+pub mod json_protocol;
+pub mod bot;
 pub mod sized_enum;
 
 #[cfg(debug_assertions)]
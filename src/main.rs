@@ -1,38 +1,174 @@
 extern crate ww;
 
-use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
 
 use ww::platform::*;
 use ww::game_state::GameState;
+use ww::map::Map;
+use ww::map_options::MapOptions;
+use ww::command::ParserMode;
+use ww::session::Session;
+use ww::json_protocol;
 
-thread_local!(static GAME_STATE: RefCell<GameState> = {
-  let mut state = GameState::new();
+const USAGE: &str = "Usage: werewolves-and-wanderer [--seed <number>] \
+                     [--map <file> | --random-castle <seed> \
+                     [--rooms <number>] [--floors <number>]] \
+                     [--monsters <file>] [--items <file>] [--words] \
+                     [--record <file> | --replay <file> | --json]";
 
-  state.map.populate();
+struct Options {
+  seed: Option<u64>,
+  map: Map,
+  parser: ParserMode,
+  record: Option<String>,
+  replay: Option<String>,
+  json: bool,
+}
+
+impl Options {
+  fn from_args() -> Result<Self, String> {
+    let mut options = Self {
+      seed: None,
+      map: Map::castle(),
+      parser: ParserMode::Classic,
+      record: None,
+      replay: None,
+      json: false,
+    };
+    let mut map_options = MapOptions::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--seed" => {
+          let value = args.next().unwrap_or_default();
+          match value.parse::<u64>() {
+            Ok(seed) => { options.seed = Some(seed) },
+            Err(_) => {
+              return Err(format!("Invalid seed: {:?}", value));
+            }
+          }
+        },
+        "--words" => { options.parser = ParserMode::Words },
+        "--json" => { options.json = true },
+        "--record" | "--replay" => {
+          let path = args.next().ok_or_else(|| {
+            format!("{} requires a filename", arg)
+          })?;
+          if arg == "--record" {
+            options.record = Some(path);
+          } else {
+            options.replay = Some(path);
+          }
+        },
+        _ => {
+          if !map_options.parse_arg(&arg, &mut args)? {
+            return Err(format!("Unknown argument: {}", arg));
+          }
+        },
+      }
+    }
+
+    options.map = map_options.build()?;
+
+    if options.json && (options.record.is_some() ||
+                        options.replay.is_some()) {
+      return Err(String::from("--json can't be combined with --record \
+                               or --replay"));
+    }
 
-  RefCell::new(state)
-});
+    Ok(options)
+  }
+}
 
 fn main() {
+  let options = match Options::from_args() {
+    Ok(options) => options,
+    Err(message) => {
+      eprintln!("{}", message);
+      eprintln!("{}", USAGE);
+      process::exit(1);
+    }
+  };
+
+  if let Some(path) = options.replay {
+    replay(&path, options.map);
+    return;
+  }
+
+  if options.json {
+    let seed = options.seed.unwrap_or_else(Platform::random_seed);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = json_protocol::play(seed, options.map, options.parser,
+                                        stdin.lock(), stdout.lock()) {
+      eprintln!("Error playing over JSON: {}", e);
+      process::exit(1);
+    }
+    return;
+  }
+
   Platform::init();
 
-  Platform::clear_screen();
+  let seed = options.seed.unwrap_or_else(Platform::random_seed);
+  let mut state = GameState::new(seed, options.map,
+                                 Box::new(Platform::new()));
+  state.parser = options.parser;
 
-  Platform::set_main_loop_callback(|| game_state_tick());
+  let record_path = options.record;
+  if record_path.is_some() {
+    let session = Session::new(seed, state.parser, &state.map);
+    state.platform.start_recording(session);
+  }
+
+  state.map.populate(&mut state.rng);
+
+  state.platform.clear_screen();
+
+  Platform::set_main_loop_callback(move || {
+    tick(&mut state, &record_path)
+  });
 }
- 
-#[no_mangle]
-pub extern fn game_state_tick() {
-  GAME_STATE.with(|refcell| {
-    let ref mut state = *refcell.borrow_mut();
 
-    state.tick();
-    while !state.is_waiting_for_input() {
-      state.tick();
-      if state.is_finished() {
-        Platform::terminate_program();
-        return;
+fn replay(path: &str, map: Map) {
+  let result = fs::read_to_string(path)
+    .map_err(|e| e.to_string())
+    .and_then(|data| Session::parse(&data))
+    .and_then(|session| session.replay(map));
+
+  match result {
+    Ok(()) => { println!("Replay of {} matches.", path) },
+    Err(message) => {
+      eprintln!("Replay of {} failed: {}", path, message);
+      process::exit(1);
+    }
+  }
+}
+
+// We save the whole session after every tick, so that even if the
+// player just closes their terminal, we'll have a record of it.
+fn save_recorded_session(state: &GameState, record_path: &Option<String>) {
+  if let Some(ref path) = *record_path {
+    if let Some(session) = state.platform.recorded_session() {
+      if let Err(e) = fs::write(path, session.to_string()) {
+        eprintln!("Unable to write session to {}: {}", path, e);
       }
     }
-  });
+  }
+}
+
+fn tick(state: &mut GameState, record_path: &Option<String>) {
+  state.tick();
+  while !state.is_waiting_for_input() && !state.is_finished() {
+    state.tick();
+  }
+  save_recorded_session(state, record_path);
+
+  // If our input has gone away, there's no point in waiting for more.
+  if state.is_finished() || state.platform.is_disconnected() {
+    Platform::terminate_program();
+  }
 }
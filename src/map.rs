@@ -1,68 +1,90 @@
-use platform::*;
+use rng::Rng;
 use direction::Direction;
-use direction::Direction::*;
-use monsters::MonsterId;
+use monsters::{Bestiary, MonsterId};
+use items::Catalogue;
+use triggers::{Trigger, TriggerAction};
 use sized_enum::SizedEnum;
 
-use self::RoomId::*;
 use self::RoomContents::*;
 
-const NUM_ROOMS: usize = 19;
+// The castle from the book, which is used unless the player asks
+// for a different map. See `map_file` for details on the format.
+const CASTLE_MAP: &str = include_str!("../maps/castle.txt");
+
 const NUM_ROOMS_WITH_TREASURE: usize = 4;
-const NUM_ROOMS_WITH_TERROR: usize = 4;
 const MIN_TREASURE_AMOUNT: u8 = 10;
 const MAX_TREASURE_AMOUNT: u8 = 110;
 
-enum_from_primitive! {
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum RoomId {
-  Hallway = 0,
-  AudienceChamber = 1,
-  GreatHall = 2,
-  PrivateMeeting = 3,
-  InnerHallway = 4,
-  Entrance = 5,
-  Kitchen = 6,
-  StoreRoom = 7,
-  Lift = 8,
-  RearVestibule = 9,
-  Exit = 10,
-  Dungeon = 11,
-  Guardroom = 12,
-  MasterBedroom = 13,
-  UpperHallway = 14,
-  Treasury = 15,
-  ChambermaidsBedroom = 16,
-  DressingChamber = 17,
-  SmallRoom = 18,
-}
-}
+// Map fingerprints use the FNV-1a hash, which unlike the standard
+// library's hasher is guaranteed to be the same in every build.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
 
-impl SizedEnum for RoomId {
-  fn size() -> usize { NUM_ROOMS }
-}
+// Rooms are identified by the order in which they were added to
+// the map.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RoomId(pub usize);
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RoomContents {
   Treasure(u8),
   Terror(MonsterId),
 }
 
+#[derive(Clone)]
 pub struct Map {
   rooms: Vec<Room>,
+  pub entrance: RoomId,
+  treasure_rooms: Vec<RoomId>,
+  pub monsters: Bestiary,
+  pub catalogue: Catalogue,
 }
 
 impl Map {
-  pub fn new() -> Self {
-    Self { rooms: vec![Room::new(); RoomId::size()] }
+  // Creates an empty map, inhabited by the monsters from the book and
+  // selling the book's items. Until told otherwise, the first room
+  // added to it will be its entrance.
+  pub(crate) fn new() -> Self {
+    Self {
+      rooms: Vec::new(),
+      entrance: RoomId(0),
+      treasure_rooms: Vec::new(),
+      monsters: Bestiary::classic(),
+      catalogue: Catalogue::classic(),
+    }
+  }
+
+  pub fn castle() -> Self {
+    Self::parse(CASTLE_MAP).expect("The built-in castle map is invalid")
   }
 
   pub fn room(&self, r: RoomId) -> &Room {
-    &self.rooms[r as usize]
+    &self.rooms[r.0]
   }
 
   pub fn mut_room(&mut self, r: RoomId) -> &mut Room {
-    &mut self.rooms[r as usize]
+    &mut self.rooms[r.0]
+  }
+
+  pub fn add_room(&mut self, key: &str) -> RoomId {
+    self.rooms.push(Room::new(key));
+    RoomId(self.rooms.len() - 1)
+  }
+
+  pub fn num_rooms(&self) -> usize {
+    self.rooms.len()
+  }
+
+  pub fn room_ids(&self) -> impl Iterator<Item = RoomId> {
+    (0..self.rooms.len()).map(RoomId)
+  }
+
+  pub fn find_room(&self, key: &str) -> Option<RoomId> {
+    self.rooms.iter().position(|room| room.key == key).map(RoomId)
+  }
+
+  pub fn random_room(&self, rng: &mut Rng) -> RoomId {
+    RoomId(rng.random_i32(0, self.rooms.len() as i32) as usize)
   }
 
   pub fn connect(&mut self, from: RoomId, d: Direction,
@@ -72,24 +94,172 @@ impl Map {
     self
   }
 
-  pub fn populate(&mut self) {
-    self.describe_and_connect();
-    self.allot_treasure();
-    self.allot_terror();
-    self.ensure_treasure();
+  // Adds an exit that can't be used to come back the same way.
+  pub fn connect_one_way(&mut self, from: RoomId, d: Direction,
+                         to: RoomId) -> &mut Self {
+    self.mut_room(from).set_exit(d, to);
+    self
+  }
+
+  // Calls the given function on the exit, along with the exit on the
+  // other side of it if that leads straight back, since both are the
+  // same door as far as the player is concerned.
+  fn update_door<F>(&mut self, r: RoomId, d: Direction, update: F)
+    where F: Fn(&mut Exit)
+  {
+    let to = match self.mut_room(r).mut_exit(d) {
+      Some(exit) => { update(exit); exit.to },
+      None => { return; },
+    };
+    if let Some(exit) = self.mut_room(to).mut_exit(d.opposite()) {
+      if exit.to == r {
+        update(exit);
+      }
+    }
+  }
+
+  // Locks the door in the given direction, so that it can only be
+  // opened with the item that has the given key.
+  pub fn lock_exit(&mut self, r: RoomId, d: Direction, item_key: &str) {
+    self.update_door(r, d, |exit| {
+      exit.lock = Some(String::from(item_key));
+      exit.locked = true;
+    });
+  }
+
+  pub fn unlock_exit(&mut self, r: RoomId, d: Direction) {
+    self.update_door(r, d, |exit| { exit.locked = false });
+  }
+
+  // Hides the exit in the given direction until the player searches
+  // for it. Only this side of the exit is hidden.
+  pub fn hide_exit(&mut self, r: RoomId, d: Direction) {
+    if let Some(exit) = self.mut_room(r).mut_exit(d) {
+      exit.secret = true;
+      exit.hidden = true;
+    }
+  }
+
+  // Once a secret passage has been found from one side, the player
+  // knows about it from the other side too.
+  pub fn reveal_exit(&mut self, r: RoomId, d: Direction) {
+    self.update_door(r, d, |exit| { exit.hidden = false });
+  }
+
+  // Puts every exit back the way the map describes it, with all its
+  // locks locked and all its secrets hidden.
+  pub fn reset_exits(&mut self) {
+    for room in self.rooms.iter_mut() {
+      for exit in room.exits.iter_mut().flat_map(|exit| exit.iter_mut()) {
+        exit.locked = exit.lock.is_some();
+        exit.hidden = exit.secret;
+      }
+    }
+  }
+
+  // A number that identifies the map, so that a saved game can tell
+  // whether it's being resumed on the map it was saved on. Only the
+  // parts of the map that stay the same throughout a game count.
+  pub fn fingerprint(&self) -> u64 {
+    let mut parts = vec![format!("entrance {}", self.entrance.0)];
+    for room_id in self.treasure_rooms.iter() {
+      parts.push(format!("treasure {}", room_id.0));
+    }
+    for room in self.rooms.iter() {
+      parts.push(format!("room {}", room.key));
+      for exit in room.exits.iter() {
+        parts.push(match *exit {
+          Some(ref exit) => {
+            format!("exit {} {:?} {}", exit.to.0, exit.lock, exit.secret)
+          },
+          None => String::from("no exit"),
+        });
+      }
+      for trigger in room.triggers.iter() {
+        parts.push(format!("{:?}", trigger));
+      }
+    }
+    for id in self.monsters.ids() {
+      parts.push(format!("monster {}", self.monsters.get(id).key));
+    }
+    for id in self.catalogue.ids() {
+      parts.push(format!("item {}", self.catalogue.get(id).key));
+    }
+
+    parts.join("\n").bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+      (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+  }
+
+  pub fn add_trigger(&mut self, r: RoomId, trigger: Trigger) {
+    self.mut_room(r).triggers.push(trigger);
+  }
+
+  // A lift is a room that, as soon as it's entered, takes the
+  // player somewhere else.
+  pub fn add_lift(&mut self, lift: RoomId, destination: RoomId) {
+    for line in ["You have entered the lift...", "It slowly descends..."] {
+      self.add_trigger(lift, Trigger::new(TriggerAction::Say(
+        String::from(line))));
+    }
+    self.add_trigger(lift, Trigger::new(TriggerAction::Teleport(destination)));
+  }
+
+  // Entering an exit wins the game.
+  pub fn add_exit(&mut self, r: RoomId) {
+    self.add_trigger(r, Trigger::new(TriggerAction::Escape));
+  }
+
+  // Where entering the room always takes the player, if anywhere.
+  pub fn teleport_destination(&self, r: RoomId) -> Option<RoomId> {
+    self.room(r).triggers.iter().filter(|trigger| !trigger.once)
+      .filter_map(|trigger| match trigger.action {
+        TriggerAction::Teleport(destination) => Some(destination),
+        _ => None,
+      }).next()
+  }
+
+  pub fn is_exit(&self, r: RoomId) -> bool {
+    self.room(r).triggers.iter().any(|trigger| {
+      trigger.action == TriggerAction::Escape
+    })
+  }
+
+  pub fn exits<'a>(&'a self) -> impl Iterator<Item = RoomId> + 'a {
+    self.room_ids().filter(move |&r| self.is_exit(r))
+  }
+
+  // Treasure rooms always start the game with some treasure in them.
+  pub fn add_treasure_room(&mut self, r: RoomId) {
+    self.treasure_rooms.push(r);
+  }
+
+  pub fn populate(&mut self, rng: &mut Rng) {
+    self.allot_treasure(rng);
+    self.allot_terror(rng);
+    self.ensure_treasure(rng);
   }
 
-  fn allot<F>(&mut self, num_rooms: usize, allotter: F)
-    where F: Fn() -> RoomContents
+  // Whether the room can have treasure or a monster in it.
+  pub fn can_hold_contents(&self, r: RoomId) -> bool {
+    r != self.entrance &&
+      !self.room(r).triggers.iter().any(Trigger::is_transient)
+  }
+
+  fn allot<F>(&mut self, rng: &mut Rng, num_rooms: usize, allotter: F)
+    where F: Fn(&mut Rng) -> RoomContents
   {
-    for _ in 0..num_rooms {
+    let num_free_rooms = self.room_ids().filter(|&r| {
+      self.can_hold_contents(r) && self.room(r).contents.is_none()
+    }).count();
+
+    for _ in 0..num_rooms.min(num_free_rooms) {
       loop {
-        let room_id = RoomId::random();
-        if room_id != Entrance && room_id != Exit &&
-           room_id != Lift {
+        let room_id = self.random_room(rng);
+        if self.can_hold_contents(room_id) {
           let room = self.mut_room(room_id);
           if room.contents.is_none() {
-            let contents = allotter();
+            let contents = allotter(rng);
             room.contents = Some(contents);
             break;
           }
@@ -98,218 +268,130 @@ impl Map {
     }
   }
 
-  fn allot_terror(&mut self) {
-    self.allot(NUM_ROOMS_WITH_TERROR, || Terror(MonsterId::random()))
+  fn allot_terror(&mut self, rng: &mut Rng) {
+    let monsters = self.monsters.clone();
+    self.allot(rng, monsters.lairs, |rng| Terror(monsters.random(rng)))
   }
 
-  fn allot_treasure(&mut self) {
-    self.allot(NUM_ROOMS_WITH_TREASURE,
-               || Treasure(random_treasure_amount()))
+  fn allot_treasure(&mut self, rng: &mut Rng) {
+    self.allot(rng, NUM_ROOMS_WITH_TREASURE,
+               |rng| Treasure(random_treasure_amount(rng)))
   }
 
-  fn ensure_treasure(&mut self) {
-    for &room_id in [Treasury, PrivateMeeting].iter() {
-      let amount = random_treasure_amount();
+  fn ensure_treasure(&mut self, rng: &mut Rng) {
+    for room_id in self.treasure_rooms.clone() {
+      let amount = random_treasure_amount(rng);
       self.mut_room(room_id).contents = Some(Treasure(amount));
     }
   }
+}
 
-  fn describe_and_connect(&mut self) {
-    self.mut_room(Entrance).describe(
-      "Entrance",
-      "You are at the entrance to a forbidding-looking \
-       stone castle. You are facing east."
-    );
-
-    self.mut_room(Hallway).describe(
-      "Hallway",
-      "You are in the hallway. \
-       There is a door to the south. \
-       Through the windows to the north you can see a secret herb garden."
-    );
-
-    self.mut_room(AudienceChamber).describe(
-      "Audience Chamber",
-      "This is the audience chamber. \
-       There is a window to the west. By looking to the right \
-       through it you can see the entrance to the castle. \
-       Doors leave this room to the north, east, and south."
-    );
-
-    self.mut_room(GreatHall).describe(
-      "Great Hall",
-      "You are in the great hall, an L-shaped room. \
-       There are doors to the east and to the north. \
-       In the alcove is a door to the west."
-    );
-
-    self.mut_room(PrivateMeeting).describe(
-      "Private Meeting Room",
-      "This is the monarch's private meeting room. \
-       There is a single exit to the south."
-    );
-
-    self.mut_room(InnerHallway).describe(
-      "Inner Hallway",
-      "This inner hallway contains a door to the north, \
-       and one to the west, and a circular stairwell \
-       passes through the room. \
-       You can see an ornamental lake through the \
-       windows to the south."
-    );
-
-    self.mut_room(Kitchen).describe(
-      "Kitchen",
-      "This is the castle's kitchen. Through windows in \
-       the north wall you can see a secret herb garden. \
-       A door leaves the kitchen to the south."
-    );
-
-    self.mut_room(StoreRoom).describe(
-      "Store Room",
-      "You are in the store room, amidst spices, \
-       vegetables, and vast sacks of flour and \
-       other provisions. There is a door to the north \
-       and one to the south."
-    );
-
-    self.mut_room(RearVestibule).describe(
-      "Rear Vestibule",
-      "You are in the rear vestibule. \
-       There are windows to the south from which \
-       you can see the ornamental lake. \
-       There is an exit to the east, and \
-       one to the north."
-    );
-
-    self.mut_room(Dungeon).describe(
-      "Dungeon",
-      "You are in the dank, dark dungeon. \
-       There is a single exit, a small hole in \
-       the wall towards the west."
-    );
-
-    self.mut_room(Guardroom).describe(
-      "Guardroom",
-      "You are in the prison guardroom, in the \
-       basement of the castle. The stairwell \
-       ends in this room. There is one other \
-       exit, a small hole in the east wall."
-    );
-
-    self.mut_room(MasterBedroom).describe(
-      "Master Bedroom",
-      "You are in the master bedroom on the upper \
-       level of the castle.... \
-       Looking down from the window to the west you \
-       can see the entrance to the castle, while the \
-       secret herb garden is visible below the north \
-       window. There are doors to the east and \
-       to the south...."
-    );
-
-    self.mut_room(UpperHallway).describe(
-      "Upper Hallway",
-      "This is the L-shaped upper hallway. \
-       To the north is a door, and there is a \
-       stairwell in the hall as well. You can see \
-       the lake through the south windows."
-    );
-
-    self.mut_room(Treasury).describe(
-      "Treasury",
-      "This room was used as the castle treasury in \
-       by-gone years.... \
-       There are no windows, just exits to the \
-       north and to the east."
-    );
-
-    self.mut_room(ChambermaidsBedroom).describe(
-      "Chambermaids' Bedroom",
-      "Ooooh.... You are in the chambermaids' bedroom. \
-       There is an exit to the west and a door \
-       to the south...."
-      // There's also a door to the north, but the book's
-      // original description of this room doesn't include it.
-      // Not sure if this means that it's meant to be a secret
-      // or just a copy error.
-    );
-
-    self.mut_room(DressingChamber).describe(
-      "Dressing Chamber",
-      "This tiny room on the upper level is the \
-       dressing chamber. There is a window to the \
-       north, with a view of the herb garden down \
-       below. A door leaves to the south."
-    );
-
-    self.mut_room(SmallRoom).describe(
-      "Small Room",
-      "This is the small room outside the castle \
-       lift which can be entered by a door to the north. \
-       Another door leads to the west. You can see \
-       the lake through the southern windows."
-    );
-
-    self.connect(Entrance, East, Hallway);
-    self.connect(Hallway, South, AudienceChamber);
-    self.connect(GreatHall, North, AudienceChamber);
-    self.connect(GreatHall, West, AudienceChamber);
-    self.connect(PrivateMeeting, South, InnerHallway);
-    self.connect(InnerHallway, West, GreatHall);
-    self.connect(InnerHallway, Up, UpperHallway);
-    self.connect(Kitchen, South, StoreRoom);
-    self.connect(StoreRoom, South, RearVestibule);
-    self.connect(RearVestibule, East, Exit);
-    self.connect(Dungeon, West, Guardroom);
-    self.connect(Guardroom, Up, InnerHallway);
-    self.connect(MasterBedroom, South, UpperHallway);
-    self.connect(MasterBedroom, East, ChambermaidsBedroom);
-    self.connect(Treasury, North, ChambermaidsBedroom);
-    self.connect(Treasury, East, SmallRoom);
-    self.connect(ChambermaidsBedroom, North, DressingChamber);
-    self.connect(SmallRoom, North, Lift);
-  }
+fn random_treasure_amount(rng: &mut Rng) -> u8 {
+  rng.random_i32(MIN_TREASURE_AMOUNT as i32,
+                 MAX_TREASURE_AMOUNT as i32) as u8
 }
 
-fn random_treasure_amount() -> u8 {
-  Platform::random_i32(MIN_TREASURE_AMOUNT as i32,
-                       MAX_TREASURE_AMOUNT as i32) as u8
+// A way out of a room, which only goes in one direction; the way back
+// is a separate exit in the room it leads to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exit {
+  pub to: RoomId,
+
+  // The key of the item that unlocks the exit, if it has a lock, and
+  // whether it's currently locked.
+  pub lock: Option<String>,
+  pub locked: bool,
+
+  // Whether the exit is a secret passage, and if so, whether the
+  // player has yet to find it.
+  pub secret: bool,
+  pub hidden: bool,
+}
+
+impl Exit {
+  pub fn new(to: RoomId) -> Self {
+    Self { to, lock: None, locked: false, secret: false, hidden: false }
+  }
 }
 
 #[derive(Debug, Clone)]
 pub struct Room {
-  exits: Vec<Option<RoomId>>,
-  pub name: &'static str,
-  pub description: &'static str,
+  exits: Vec<Option<Exit>>,
+  pub key: String,
+  pub name: String,
+  pub description: String,
   pub contents: Option<RoomContents>,
+  pub triggers: Vec<Trigger>,
 }
 
 impl Room {
-  pub fn new() -> Self {
+  pub fn new(key: &str) -> Self {
     Self {
       exits: vec![None; Direction::size()],
-      name: "",
-      description: "",
+      key: String::from(key),
+      name: String::new(),
+      description: String::new(),
       contents: None,
+      triggers: Vec::new(),
     }
   }
 
+  // Where the exit in the given direction leads, as far as the player
+  // can tell: secret passages they haven't found don't count, but
+  // locked doors do.
   pub fn get_exit(&self, d: Direction) -> Option<RoomId> {
-    self.exits[d as usize]
+    self.exit(d).filter(|exit| !exit.hidden).map(|exit| exit.to)
+  }
+
+  // Where the exit in the given direction leads, if the player can go
+  // through it right now.
+  pub fn get_open_exit(&self, d: Direction) -> Option<RoomId> {
+    self.exit(d).filter(|exit| !exit.hidden && !exit.locked)
+      .map(|exit| exit.to)
+  }
+
+  pub fn exit(&self, d: Direction) -> Option<&Exit> {
+    self.exits[d as usize].as_ref()
+  }
+
+  pub fn mut_exit(&mut self, d: Direction) -> Option<&mut Exit> {
+    self.exits[d as usize].as_mut()
   }
 
   pub fn set_exit(&mut self, d: Direction, r: RoomId) -> &mut Self {
     assert!(self.exits[d as usize].is_none());
-    self.exits[d as usize] = Some(r);
+    self.exits[d as usize] = Some(Exit::new(r));
     self
   }
 
-  pub fn describe(&mut self, name: &'static str,
-                  desc: &'static str) -> &mut Self {
-    assert_eq!(self.name, "");
-    self.name = name;
-    self.description = desc;
+  pub fn describe(&mut self, name: &str, desc: &str) -> &mut Self {
+    self.name = String::from(name);
+    self.description = String::from(desc);
     self
   }
 }
+
+#[test]
+fn test_same_seed_gives_same_castle() {
+  let populate = |seed| {
+    let mut map = Map::castle();
+    map.populate(&mut Rng::new(seed));
+    map.room_ids().map(|id| map.room(id).contents).collect::<Vec<_>>()
+  };
+  assert_eq!(populate(1234), populate(1234));
+  assert!(populate(1234) != populate(4321));
+}
+
+#[test]
+fn test_populate_skips_special_rooms() {
+  let mut map = Map::castle();
+  map.populate(&mut Rng::new(1));
+
+  let lift = map.find_room("Lift").unwrap();
+  let exit = map.find_room("Exit").unwrap();
+  let treasury = map.find_room("Treasury").unwrap();
+  assert!(map.room(map.entrance).contents.is_none());
+  assert!(map.room(exit).contents.is_none());
+  assert!(map.room(lift).contents.is_none());
+  assert!(map.room(treasury).contents.is_some());
+}
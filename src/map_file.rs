@@ -0,0 +1,385 @@
+use direction::Direction;
+use sized_enum::SizedEnum;
+use map::{Map, RoomId};
+use triggers::{Trigger, TriggerAction};
+
+// Maps are described in a simple line-based text format, e.g.:
+//
+//   # Lines starting with '#' are comments.
+//   room Entrance
+//   name Entrance
+//   description You are at the entrance to a forbidding-looking
+//     stone castle. You are facing east.
+//
+//   room Hallway
+//   name Hallway
+//   description You are in the hallway.
+//
+//   connect Entrance east Hallway
+//   entrance Entrance
+//   exit Hallway
+//
+// Lines that start with whitespace continue the previous line. The
+// directives are:
+//
+//   room <key>                  - start describing a new room
+//   name <text>                 - the current room's name
+//   description <text>          - the current room's description
+//   connect <key> <dir> <key>   - connect two rooms in both directions
+//   passage <key> <dir> <key>   - connect the first room to the second,
+//                                 but not the other way around
+//   lock <key> <dir> <item>     - lock the door in that direction (from
+//                                 both sides); the item with the given
+//                                 key from the shop unlocks it
+//   secret <key> <dir>          - hide the exit in that direction (from
+//                                 this side only) until the player
+//                                 searches for it
+//   entrance <key>              - where the player starts
+//   on <key> <action>           - something that happens whenever the
+//                                 player enters the room (see below)
+//   on <key> once <action>      - the same, but only the first time
+//   exit <key>                  - short for `on <key> escape`
+//   lift <key> <key>            - short for saying that the player has
+//                                 entered a lift, which takes them to
+//                                 the second room
+//   treasure <key>              - the room always starts with treasure
+//
+// The actions that can happen when a room is entered are:
+//
+//   say <text>                  - tell the player something
+//   teleport <key>              - take the player to another room
+//   escape                      - the player escapes the castle and
+//                                 wins
+//   damage <number>             - the player loses this much strength
+//   give <item> [<number>]      - the player gets the item with the
+//                                 given key from the shop, or that many
+//                                 units of it if it's stackable
+//
+// A room's actions happen in the order they're declared, until one of
+// them takes the player out of the room.
+//
+// Room keys can't contain whitespace, and every room must be declared
+// with `room` before it's referred to by any other directive. Every map
+// needs at least one exit.
+
+fn parse_direction(name: &str) -> Result<Direction, String> {
+  Direction::iter().find(|dir| dir.to_string() == name).ok_or_else(|| {
+    format!("Unknown direction: {}", name)
+  })
+}
+
+// Joins continuation lines onto the lines they continue, returning
+// each logical line along with the line number it started on.
+pub fn logical_lines(data: &str) -> Vec<(usize, String)> {
+  let mut lines: Vec<(usize, String)> = Vec::new();
+
+  for (i, line) in data.lines().enumerate() {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+      continue;
+    }
+    if line.starts_with(char::is_whitespace) {
+      if let Some(&mut (_, ref mut prev)) = lines.last_mut() {
+        prev.push(' ');
+        prev.push_str(trimmed);
+        continue;
+      }
+    }
+    lines.push((i + 1, String::from(trimmed)));
+  }
+
+  lines
+}
+
+struct MapParser {
+  map: Map,
+  curr_room: Option<RoomId>,
+  has_entrance: bool,
+}
+
+impl MapParser {
+  fn room(&self, key: &str) -> Result<RoomId, String> {
+    self.map.find_room(key).ok_or_else(|| format!("Unknown room: {}", key))
+  }
+
+  fn exit(&self, key: &str, dir: &str) -> Result<(RoomId, Direction), String> {
+    let room_id = self.room(key)?;
+    let dir = parse_direction(dir)?;
+    if self.map.room(room_id).exit(dir).is_none() {
+      return Err(format!("{} has no exit to the {}", key, dir));
+    }
+    Ok((room_id, dir))
+  }
+
+  fn parse_action(&self, action: &str) -> Result<TriggerAction, String> {
+    let (verb, rest) = match action.find(' ') {
+      Some(i) => (&action[..i], action[i + 1..].trim()),
+      None => (action, ""),
+    };
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let parse_number = |value: &str| {
+      value.parse::<i32>().ok().filter(|&n| n > 0).ok_or_else(|| {
+        format!("Invalid number for '{}': {}", verb, value)
+      })
+    };
+
+    match (verb, args.len()) {
+      ("say", n) if n > 0 => Ok(TriggerAction::Say(String::from(rest))),
+      ("teleport", 1) => Ok(TriggerAction::Teleport(self.room(args[0])?)),
+      ("escape", 0) => Ok(TriggerAction::Escape),
+      ("damage", 1) => Ok(TriggerAction::Damage(parse_number(args[0])?)),
+      ("give", 1) => Ok(TriggerAction::Give(String::from(args[0]), 1)),
+      ("give", 2) => {
+        Ok(TriggerAction::Give(String::from(args[0]), parse_number(args[1])?))
+      },
+      _ => Err(format!("Invalid action: {}", action)),
+    }
+  }
+
+  fn curr_room(&self, directive: &str) -> Result<RoomId, String> {
+    self.curr_room.ok_or_else(|| {
+      format!("'{}' must come after a 'room' directive", directive)
+    })
+  }
+
+  fn parse_directive(&mut self, directive: &str,
+                     rest: &str) -> Result<(), String> {
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let expect_args = |n: usize| {
+      if args.len() == n {
+        Ok(())
+      } else {
+        Err(format!("'{}' expects {} argument{}", directive, n,
+                    if n == 1 { "" } else { "s" }))
+      }
+    };
+
+    match directive {
+      "room" => {
+        expect_args(1)?;
+        if self.map.find_room(args[0]).is_some() {
+          return Err(format!("Room {} is declared twice", args[0]));
+        }
+        self.curr_room = Some(self.map.add_room(args[0]));
+      },
+      "name" => {
+        let room_id = self.curr_room(directive)?;
+        self.map.mut_room(room_id).name = String::from(rest);
+      },
+      "description" => {
+        let room_id = self.curr_room(directive)?;
+        self.map.mut_room(room_id).description = String::from(rest);
+      },
+      "connect" | "passage" => {
+        expect_args(3)?;
+        let from = self.room(args[0])?;
+        let dir = parse_direction(args[1])?;
+        let to = self.room(args[2])?;
+        if self.map.room(from).exit(dir).is_some() {
+          return Err(format!("{} already has an exit to the {}",
+                             args[0], dir));
+        }
+        if directive == "passage" {
+          self.map.connect_one_way(from, dir, to);
+          return Ok(());
+        }
+        if self.map.room(to).exit(dir.opposite()).is_some() {
+          return Err(format!("{} already has an exit to the {}",
+                             args[2], dir.opposite()));
+        }
+        self.map.connect(from, dir, to);
+      },
+      "lock" => {
+        expect_args(3)?;
+        let (room_id, dir) = self.exit(args[0], args[1])?;
+        self.map.lock_exit(room_id, dir, args[2]);
+      },
+      "secret" => {
+        expect_args(2)?;
+        let (room_id, dir) = self.exit(args[0], args[1])?;
+        self.map.hide_exit(room_id, dir);
+      },
+      "entrance" => {
+        expect_args(1)?;
+        self.map.entrance = self.room(args[0])?;
+        self.has_entrance = true;
+      },
+      "on" => {
+        if args.len() < 2 {
+          return Err(String::from("'on' expects a room and an action"));
+        }
+        let room_id = self.room(args[0])?;
+        let action = rest[args[0].len()..].trim_start();
+        let trigger = match action.strip_prefix("once ") {
+          Some(action) => Trigger::once(self.parse_action(action.trim())?),
+          None => Trigger::new(self.parse_action(action)?),
+        };
+        self.map.add_trigger(room_id, trigger);
+      },
+      "exit" => {
+        expect_args(1)?;
+        let room_id = self.room(args[0])?;
+        self.map.add_exit(room_id);
+      },
+      "lift" => {
+        expect_args(2)?;
+        let lift = self.room(args[0])?;
+        let destination = self.room(args[1])?;
+        self.map.add_lift(lift, destination);
+      },
+      "treasure" => {
+        expect_args(1)?;
+        let room_id = self.room(args[0])?;
+        self.map.add_treasure_room(room_id);
+      },
+      _ => { return Err(format!("Unknown directive: {}", directive)); }
+    }
+    Ok(())
+  }
+}
+
+impl Map {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut parser = MapParser {
+      map: Map::new(),
+      curr_room: None,
+      has_entrance: false,
+    };
+
+    for (line_number, line) in logical_lines(data) {
+      let (directive, rest) = match line.find(' ') {
+        Some(i) => (&line[..i], line[i + 1..].trim()),
+        None => (line.as_str(), ""),
+      };
+      parser.parse_directive(directive, rest).map_err(|message| {
+        format!("Line {}: {}", line_number, message)
+      })?;
+    }
+
+    if parser.map.num_rooms() == 0 {
+      return Err(String::from("The map has no rooms."));
+    }
+    if !parser.has_entrance {
+      return Err(String::from("The map has no entrance."));
+    }
+    if parser.map.exits().next().is_none() {
+      return Err(String::from("The map has no exit."));
+    }
+    if let Some(&room_id) = parser.map.endless_teleports().first() {
+      return Err(format!("Entering {} teleports the player around in \
+                          circles.", parser.map.room(room_id).key));
+    }
+
+    Ok(parser.map)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use map::Map;
+  use direction::Direction::*;
+  use triggers::Trigger;
+  use triggers::TriggerAction::*;
+
+  const TINY_MAP: &str = "
+    room Porch
+    name Porch
+    description A porch.
+      It is creaky.
+
+    room Attic
+    name Attic
+    # The attic is the way out.
+    connect Porch up Attic
+    entrance Porch
+    exit Attic
+  ";
+
+  #[test]
+  fn test_parse_tiny_map() {
+    let map = Map::parse(&TINY_MAP.replace("\n    ", "\n")).unwrap();
+    let porch = map.find_room("Porch").unwrap();
+    let attic = map.find_room("Attic").unwrap();
+
+    assert_eq!(map.num_rooms(), 2);
+    assert_eq!(map.entrance, porch);
+    assert!(map.is_exit(attic));
+    assert_eq!(map.room(porch).description, "A porch. It is creaky.");
+    assert_eq!(map.room(porch).get_exit(Up), Some(attic));
+    assert_eq!(map.room(attic).get_exit(Down), Some(porch));
+  }
+
+  #[test]
+  fn test_parse_errors_have_line_numbers() {
+    let map = TINY_MAP.replace("\n    ", "\n");
+
+    assert_eq!(Map::parse(&map.replace("up Attic", "sideways Attic")).err(),
+               Some(String::from("Line 10: Unknown direction: sideways")));
+    assert_eq!(Map::parse(&map.replace("exit Attic", "exit Basement")).err(),
+               Some(String::from("Line 12: Unknown room: Basement")));
+    assert_eq!(Map::parse(&map.replace("exit Attic", "")).err(),
+               Some(String::from("The map has no exit.")));
+    assert!(Map::parse(&format!("{}\nconnect Attic down Porch", map))
+            .is_err());
+    assert_eq!(Map::parse(&format!("{}\nsecret Attic east", map)).err(),
+               Some(String::from("Line 14: Attic has no exit to the east")));
+  }
+
+  #[test]
+  fn test_parse_locked_and_secret_exits() {
+    let map = Map::parse(&format!("{}
+room Cellar
+passage Porch down Cellar
+lock Porch up key
+secret Porch down", TINY_MAP.replace("\n    ", "\n"))).unwrap();
+    let porch = map.find_room("Porch").unwrap();
+    let attic = map.find_room("Attic").unwrap();
+    let cellar = map.find_room("Cellar").unwrap();
+
+    assert_eq!(map.room(porch).exit(Up).unwrap().lock,
+               Some(String::from("key")));
+    assert!(map.room(attic).exit(Down).unwrap().locked);
+    assert_eq!(map.room(porch).get_exit(Down), None);
+    assert_eq!(map.room(porch).exit(Down).unwrap().to, cellar);
+    assert_eq!(map.room(cellar).exit(Up), None);
+  }
+
+  #[test]
+  fn test_parse_triggers() {
+    let map = Map::parse(&format!("{}
+on Porch once say Mind the
+  gap.
+on Porch give food 3
+on Attic teleport Porch", TINY_MAP.replace("\n    ", "\n"))).unwrap();
+    let porch = map.find_room("Porch").unwrap();
+    let attic = map.find_room("Attic").unwrap();
+
+    assert_eq!(map.room(porch).triggers, vec![
+      Trigger::once(Say(String::from("Mind the gap."))),
+      Trigger::new(Give(String::from("food"), 3)),
+    ]);
+    assert_eq!(map.teleport_destination(attic), Some(porch));
+    assert_eq!(map.teleport_destination(porch), None);
+    assert!(map.is_exit(attic));
+
+    let map = TINY_MAP.replace("\n    ", "\n");
+    assert_eq!(Map::parse(&format!("{}\non Porch damage -5", map)).err(),
+               Some(String::from("Line 14: Invalid number for 'damage': -5")));
+    assert_eq!(Map::parse(&format!("{}\non Porch dance", map)).err(),
+               Some(String::from("Line 14: Invalid action: dance")));
+    assert!(Map::parse(&format!("{}\non Porch", map)).is_err());
+    assert_eq!(Map::parse(&format!("{}\non Porch teleport Porch", map))
+                 .err(),
+               Some(String::from("Entering Porch teleports the player \
+                                  around in circles.")));
+  }
+
+  #[test]
+  fn test_castle_map_is_valid() {
+    let map = Map::castle();
+    assert_eq!(map.num_rooms(), 19);
+    assert_eq!(map.room(map.entrance).name, "Entrance");
+    assert_eq!(map.exits().map(|r| map.room(r).key.as_str())
+               .collect::<Vec<_>>(), vec!["Exit"]);
+  }
+}
@@ -0,0 +1,343 @@
+use std::collections::{HashMap, VecDeque};
+
+use rng::Rng;
+use direction::Direction;
+use direction::Direction::*;
+use sized_enum::SizedEnum;
+use map::{Map, RoomId};
+
+// Random castles are built out of rooms like "the Dusty Library", whose
+// names are made by pairing an adjective with a kind of room. Every
+// pair is used at most once, so there are never two rooms with the
+// same name.
+const ADJECTIVES: [&str; 12] = [
+  "Dusty", "Gloomy", "Grand", "Crumbling", "Draughty", "Musty", "Gilded",
+  "Silent", "Narrow", "Damp", "Forgotten", "Smoky",
+];
+
+const KINDS: [&str; 16] = [
+  "Library", "Chapel", "Gallery", "Armoury", "Larder", "Study",
+  "Guardroom", "Solar", "Scullery", "Bedchamber", "Music Room",
+  "Trophy Room", "Nursery", "Laundry", "Map Room", "Wine Cellar",
+];
+
+// Extra sentences to make each room a little more memorable. These
+// mustn't mention exits, stairs or directions, or the map validator
+// will think the room is describing a way out.
+const DETAILS: [&str; 10] = [
+  "Cobwebs hang from the rafters.",
+  "A cold draught makes you shiver.",
+  "Straw is scattered across the floor.",
+  "Faded tapestries line the walls.",
+  "Something scurries away into the shadows.",
+  "It smells of damp and mould.",
+  "A single candle gutters in its sconce.",
+  "The flagstones are slick with moisture.",
+  "Broken furniture is piled in a corner.",
+  "Dust lies thick on every surface.",
+];
+
+const HORIZONTAL: [Direction; 4] = [North, South, East, West];
+
+// The chance that two rooms which are next to each other, but weren't
+// connected while the castle was being laid out, get an exit between
+// them anyway. Without these, every castle would be a tree with only
+// one way between any two rooms.
+const LOOP_CHANCE: f32 = 0.2;
+
+// Floors are laid out on a grid, with north being towards negative y,
+// the same way the automap draws them.
+type Position = (i32, i32, usize);
+
+fn step((x, y, floor): Position, d: Direction) -> Position {
+  match d {
+    North => (x, y - 1, floor),
+    South => (x, y + 1, floor),
+    East => (x + 1, y, floor),
+    West => (x - 1, y, floor),
+    Up => (x, y, floor + 1),
+    Down => (x, y, floor - 1),
+  }
+}
+
+// How a random castle should be generated. The same plan and seed
+// always give the same castle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CastlePlan {
+  pub rooms: usize,
+  pub floors: usize,
+}
+
+impl Default for CastlePlan {
+  fn default() -> Self {
+    Self { rooms: 20, floors: 3 }
+  }
+}
+
+// The shape of a castle before its rooms are named: where each room
+// is, and which rooms are connected to which.
+struct Layout {
+  positions: Vec<Position>,
+  rooms: HashMap<Position, usize>,
+  links: Vec<(usize, Direction, usize)>,
+}
+
+impl Layout {
+  fn add_room(&mut self, pos: Position) -> usize {
+    self.positions.push(pos);
+    self.rooms.insert(pos, self.positions.len() - 1);
+    self.positions.len() - 1
+  }
+
+  fn is_linked(&self, a: usize, b: usize) -> bool {
+    self.links.iter().any(|&(from, _, to)| {
+      (from, to) == (a, b) || (from, to) == (b, a)
+    })
+  }
+
+  // How many exits it takes to get from the first room to each room.
+  fn distances(&self) -> Vec<usize> {
+    let mut distances = vec![usize::MAX; self.positions.len()];
+    let mut queue = VecDeque::new();
+    distances[0] = 0;
+    queue.push_back(0);
+
+    while let Some(room) = queue.pop_front() {
+      for &(from, _, to) in self.links.iter() {
+        let next = if from == room { to } else if to == room { from } else {
+          continue;
+        };
+        if distances[next] == usize::MAX {
+          distances[next] = distances[room] + 1;
+          queue.push_back(next);
+        }
+      }
+    }
+
+    distances
+  }
+}
+
+fn choose<T: Copy>(rng: &mut Rng, choices: &[T]) -> T {
+  choices[rng.random_i32(0, choices.len() as i32) as usize]
+}
+
+// Joins the directions of a room's exits into a sentence like "There
+// are exits to the north and east."
+fn describe_exits(dirs: &[Direction]) -> String {
+  let horizontal: Vec<String> = dirs.iter().filter(|dir| {
+    !dir.is_vertical()
+  }).map(|dir| dir.to_string()).collect();
+  let vertical: Vec<String> = dirs.iter().filter(|dir| {
+    dir.is_vertical()
+  }).map(|dir| dir.to_string()).collect();
+  let join = |words: &[String]| match words.split_last() {
+    Some((last, [])) => last.clone(),
+    Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+    None => String::new(),
+  };
+
+  let mut sentences = Vec::new();
+  match horizontal.len() {
+    0 => {},
+    1 => {
+      sentences.push(format!("There is an exit to the {}.", horizontal[0]))
+    },
+    _ => {
+      sentences.push(format!("There are exits to the {}.", join(&horizontal)))
+    },
+  }
+  if !vertical.is_empty() {
+    sentences.push(format!("A stairwell leads {}.", join(&vertical)));
+  }
+  sentences.join(" ")
+}
+
+impl CastlePlan {
+  fn max_rooms() -> usize {
+    // The entrance and exit don't need names of their own.
+    ADJECTIVES.len() * KINDS.len() + 2
+  }
+
+  fn rooms_on_floor(&self, floor: usize) -> usize {
+    self.rooms / self.floors + if floor < self.rooms % self.floors {
+      1
+    } else {
+      0
+    }
+  }
+
+  // Lays out each floor by growing it one room at a time from a random
+  // room that's already there, so every room on a floor can be reached
+  // from every other. Each floor after the first starts with a
+  // stairwell up from somewhere on the floor below.
+  fn lay_out(&self, rng: &mut Rng) -> Layout {
+    let mut layout = Layout {
+      positions: Vec::new(),
+      rooms: HashMap::new(),
+      links: Vec::new(),
+    };
+    let mut below: Vec<usize> = Vec::new();
+
+    for floor in 0..self.floors {
+      let first = if below.is_empty() {
+        layout.add_room((0, 0, floor))
+      } else {
+        let stairs = choose(rng, &below);
+        let room = layout.add_room(step(layout.positions[stairs], Up));
+        layout.links.push((stairs, Up, room));
+        room
+      };
+
+      let mut on_floor = vec![first];
+      while on_floor.len() < self.rooms_on_floor(floor) {
+        let from = choose(rng, &on_floor);
+        let dir = choose(rng, &HORIZONTAL);
+        let pos = step(layout.positions[from], dir);
+        if !layout.rooms.contains_key(&pos) {
+          let room = layout.add_room(pos);
+          layout.links.push((from, dir, room));
+          on_floor.push(room);
+        }
+      }
+      below = on_floor;
+    }
+
+    for room in 0..layout.positions.len() {
+      for &dir in HORIZONTAL.iter() {
+        let pos = step(layout.positions[room], dir);
+        if let Some(&next) = layout.rooms.get(&pos) {
+          if !layout.is_linked(room, next) && rng.random() < LOOP_CHANCE {
+            layout.links.push((room, dir, next));
+          }
+        }
+      }
+    }
+
+    layout
+  }
+
+  pub fn generate(&self, seed: u64) -> Result<Map, String> {
+    if self.floors == 0 {
+      return Err(String::from("A castle needs at least one floor."));
+    }
+    if self.rooms < 2 || self.rooms < self.floors {
+      return Err(String::from("A castle needs at least two rooms, and at \
+                               least one on each floor."));
+    }
+    if self.rooms > Self::max_rooms() {
+      return Err(format!("A castle can have at most {} rooms.",
+                         Self::max_rooms()));
+    }
+
+    let mut rng = Rng::new(seed);
+    let layout = self.lay_out(&mut rng);
+
+    // The exit is as far from the entrance as possible. Nothing is
+    // ever reached by going through it, since every room next to it is
+    // at least as close to the entrance.
+    let distances = layout.distances();
+    let exit = (1..self.rooms).max_by_key(|&room| distances[room])
+      .expect("A castle has at least two rooms");
+
+    let mut names: Vec<(&str, &str)> = ADJECTIVES.iter().flat_map(|&adj| {
+      KINDS.iter().map(move |&kind| (adj, kind))
+    }).collect();
+    let mut map = Map::new();
+    for room in 0..self.rooms {
+      let (name, description) = if room == 0 {
+        (String::from("Entrance"),
+         String::from("You are at the entrance to a forbidding-looking \
+                       stone castle."))
+      } else if room == exit {
+        (String::from("Exit"), String::new())
+      } else {
+        let index = rng.random_i32(0, names.len() as i32) as usize;
+        let (adjective, kind) = names.swap_remove(index);
+        (format!("{} {}", adjective, kind),
+         format!("You are in the {} {}. {}", adjective.to_lowercase(),
+                 kind.to_lowercase(), choose(&mut rng, &DETAILS)))
+      };
+      let key: String = name.split_whitespace().collect();
+      let room_id = map.add_room(&key);
+      map.mut_room(room_id).describe(&name, &description);
+    }
+
+    for &(from, dir, to) in layout.links.iter() {
+      map.connect(RoomId(from), dir, RoomId(to));
+    }
+    // The exit is never described, since entering it ends the game.
+    for room_id in (0..self.rooms).filter(|&r| r != exit).map(RoomId) {
+      let dirs: Vec<Direction> = Direction::iter().filter(|&dir| {
+        map.room(room_id).exit(dir).is_some()
+      }).collect();
+      let room = map.mut_room(room_id);
+      room.description = format!("{} {}", room.description,
+                                 describe_exits(&dirs));
+    }
+
+    map.entrance = RoomId(0);
+    map.add_exit(RoomId(exit));
+    Ok(map)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bot;
+  use command::ParserMode;
+  use high_scores::Outcome;
+
+  #[test]
+  fn test_random_castles_are_solvable() {
+    for seed in 0..20 {
+      let map = CastlePlan::default().generate(seed).unwrap();
+      let report = map.validate();
+      assert_eq!(map.num_rooms(), 20);
+      assert!(report.is_exit_reachable);
+      assert_eq!(report.unreachable_rooms, vec![]);
+      assert_eq!(report.description_mismatches, vec![]);
+      assert!(map.room_ids().any(|r| map.room(r).get_exit(Up).is_some()));
+    }
+  }
+
+  #[test]
+  fn test_bot_escapes_random_castles() {
+    let map = CastlePlan { rooms: 30, floors: 4 }.generate(5).unwrap();
+    let report = bot::play_games(0..20, &map, ParserMode::Classic);
+    assert_eq!(report.problems(), Vec::<&bot::BotGame>::new());
+    assert!(report.count(Outcome::Escaped) > 0);
+  }
+
+  #[test]
+  fn test_same_seed_gives_same_random_castle() {
+    let describe = |seed| {
+      let map = CastlePlan { rooms: 12, floors: 2 }.generate(seed).unwrap();
+      map.room_ids().map(|r| map.room(r).description.clone())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(describe(7), describe(7));
+    assert!(describe(7) != describe(8));
+  }
+
+  #[test]
+  fn test_invalid_plans_fail() {
+    assert!(CastlePlan { rooms: 1, floors: 1 }.generate(1).is_err());
+    assert!(CastlePlan { rooms: 3, floors: 4 }.generate(1).is_err());
+    assert!(CastlePlan { rooms: 10, floors: 0 }.generate(1).is_err());
+    assert!(CastlePlan { rooms: 1000, floors: 1 }.generate(1).is_err());
+    assert!(CastlePlan { rooms: 2, floors: 2 }.generate(1).is_ok());
+  }
+
+  #[test]
+  fn test_exits_are_described() {
+    assert_eq!(describe_exits(&[North, East, Down]),
+               "There are exits to the north and east. A stairwell leads \
+                down.");
+    assert_eq!(describe_exits(&[South, East, West, Up]),
+               "There are exits to the south, east and west. A stairwell \
+                leads up.");
+    assert_eq!(describe_exits(&[West]), "There is an exit to the west.");
+  }
+}
@@ -0,0 +1,191 @@
+use std::fs;
+
+use map::Map;
+use map_gen::CastlePlan;
+use monsters::Bestiary;
+use items::Catalogue;
+
+// The command-line options for choosing which castle to play in, which
+// are shared by everything that plays games: --map, --random-castle
+// (with --rooms and --floors), --monsters and --items.
+#[derive(Default)]
+pub struct MapOptions {
+  map: Option<Map>,
+  castle: Option<u64>,
+  plan: CastlePlan,
+  monsters: Option<Bestiary>,
+  items: Option<Catalogue>,
+}
+
+// Returns the path to the file named by an argument, along with what's
+// in it.
+fn read_file(arg: &str,
+             path: Option<String>) -> Result<(String, String), String> {
+  let path = path.ok_or_else(|| format!("{} requires a filename", arg))?;
+  let data = fs::read_to_string(&path).map_err(|e| {
+    format!("Unable to read {}: {}", path, e)
+  })?;
+  Ok((path, data))
+}
+
+fn parse_number(arg: &str, value: Option<String>) -> Result<u64, String> {
+  let value = value.unwrap_or_default();
+  value.parse::<u64>().map_err(|_| {
+    format!("Invalid {}: {:?}", &arg[2..], value)
+  })
+}
+
+impl MapOptions {
+  // Handles the given argument if it's one of ours, taking its value
+  // from the arguments that follow it. Returns whether it was.
+  pub fn parse_arg<I>(&mut self, arg: &str,
+                      args: &mut I) -> Result<bool, String>
+    where I: Iterator<Item = String>
+  {
+    match arg {
+      "--map" => {
+        let (path, data) = read_file(arg, args.next())?;
+        self.map = Some(Map::parse(&data).map_err(|e| {
+          format!("Invalid map {}: {}", path, e)
+        })?);
+      },
+      "--random-castle" => {
+        self.castle = Some(parse_number(arg, args.next())?)
+      },
+      "--rooms" => {
+        self.plan.rooms = parse_number(arg, args.next())? as usize
+      },
+      "--floors" => {
+        self.plan.floors = parse_number(arg, args.next())? as usize
+      },
+      "--monsters" => {
+        let (path, data) = read_file(arg, args.next())?;
+        self.monsters = Some(Bestiary::parse(&data).map_err(|e| {
+          format!("Invalid monsters {}: {}", path, e)
+        })?);
+      },
+      "--items" => {
+        let (path, data) = read_file(arg, args.next())?;
+        self.items = Some(Catalogue::parse(&data).map_err(|e| {
+          format!("Invalid items {}: {}", path, e)
+        })?);
+      },
+      _ => { return Ok(false) },
+    }
+    Ok(true)
+  }
+
+  // Builds the map the options describe, which is the classic castle
+  // unless they say otherwise.
+  pub fn build(self) -> Result<Map, String> {
+    if self.map.is_some() && self.castle.is_some() {
+      return Err(String::from("--map and --random-castle can't be used \
+                               together"));
+    }
+
+    // A random castle can only be generated once we know its size.
+    let mut map = if let Some(seed) = self.castle {
+      self.plan.generate(seed)?
+    } else if self.plan != CastlePlan::default() {
+      return Err(String::from("--rooms and --floors only apply to \
+                               --random-castle"));
+    } else {
+      self.map.unwrap_or_else(Map::castle)
+    };
+
+    // Reading a map replaces its monsters and items, so these are
+    // applied last, whichever order the arguments were given in.
+    if let Some(monsters) = self.monsters {
+      map.monsters = monsters;
+    }
+    if let Some(items) = self.items {
+      map.catalogue = items;
+
+      // The new items have to include everything the castle expects the
+      // shop to sell.
+      let report = map.validate();
+      if let Some(&(room_id, dir)) = report.unopenable_locks.first() {
+        return Err(format!("{} has a lock {}, but --items doesn't include \
+                            its key.", map.room(room_id).key, dir.toward()));
+      }
+      if let Some(&(room_id, ref item)) = report.unknown_gifts.first() {
+        return Err(format!("{} gives the player {}, but --items doesn't \
+                            include it.", map.room(room_id).key, item));
+      }
+    }
+    Ok(map)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn build(args: &[&str]) -> Result<Map, String> {
+    let mut options = MapOptions::default();
+    let mut args = args.iter().map(|arg| String::from(*arg));
+    while let Some(arg) = args.next() {
+      if !options.parse_arg(&arg, &mut args)? {
+        return Err(format!("Unknown argument: {}", arg));
+      }
+    }
+    options.build()
+  }
+
+  #[test]
+  fn test_map_options() {
+    assert_eq!(build(&[]).unwrap().num_rooms(), Map::castle().num_rooms());
+    assert_eq!(build(&["--random-castle", "3", "--rooms", "8"]).unwrap()
+                 .num_rooms(), 8);
+    assert_eq!(build(&["--rooms", "8"]).err(),
+               Some(String::from("--rooms and --floors only apply to \
+                                  --random-castle")));
+    assert_eq!(build(&["--floors", "many"]).err(),
+               Some(String::from("Invalid floors: \"many\"")));
+    assert_eq!(build(&["--map"]).err(),
+               Some(String::from("--map requires a filename")));
+    assert_eq!(build(&["--words"]).err(),
+               Some(String::from("Unknown argument: --words")));
+  }
+
+  #[test]
+  fn test_map_and_random_castle_conflict() {
+    let options = MapOptions {
+      map: Some(Map::castle()),
+      castle: Some(3),
+      ..MapOptions::default()
+    };
+    assert_eq!(options.build().err(),
+               Some(String::from("--map and --random-castle can't be used \
+                                  together")));
+  }
+
+  #[test]
+  fn test_items_must_suit_the_map() {
+    let map = Map::parse("room Porch
+room Attic
+connect Porch up Attic
+entrance Porch
+exit Attic
+lock Porch up torch
+on Porch give food 3").unwrap();
+    let items = |data: &str| {
+      let options = MapOptions {
+        map: Some(map.clone()),
+        items: Some(Catalogue::parse(data).unwrap()),
+        ..MapOptions::default()
+      };
+      options.build().err()
+    };
+    let torch = "item torch\nname a torch\nprice 5\n";
+    let food = "item food\nname food\nprice 1\nstackable\nuse food 10\n";
+
+    assert_eq!(items(&format!("{}{}", torch, food)), None);
+    assert_eq!(items(food),
+               Some(String::from("Porch has a lock up, but --items \
+                                  doesn't include its key.")));
+    assert_eq!(items(torch),
+               Some(String::from("Porch gives the player food, but --items \
+                                  doesn't include it.")));
+  }
+}
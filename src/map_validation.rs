@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+
+use direction::Direction;
+use direction::Direction::*;
+use sized_enum::SizedEnum;
+use map::{Map, RoomId};
+use triggers::TriggerAction;
+
+// Words that suggest a sentence in a room's description is talking
+// about a way out of the room, rather than e.g. a window.
+const EXIT_WORDS: [&str; 9] = [
+  "door", "doors", "exit", "exits", "hole", "leads", "leave", "leaves",
+  "passage",
+];
+
+const STAIR_WORDS: [&str; 3] = ["stairwell", "stairs", "staircase"];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DescriptionMismatch {
+  // The description mentions an exit in a direction that the room
+  // doesn't actually have.
+  MissingExit(RoomId, Direction),
+
+  // The room has an exit that its description doesn't mention.
+  UndescribedExit(RoomId, Direction),
+
+  // The description mentions stairs, but there's no way up or down.
+  MissingStairs(RoomId),
+}
+
+#[derive(Debug)]
+pub struct MapReport {
+  pub unreachable_rooms: Vec<RoomId>,
+  pub is_exit_reachable: bool,
+  pub dead_ends: Vec<RoomId>,
+  pub description_mismatches: Vec<DescriptionMismatch>,
+
+  // Locked exits whose key the shop doesn't sell.
+  pub unopenable_locks: Vec<(RoomId, Direction)>,
+
+  // Rooms that give the player an item the shop doesn't sell, along
+  // with the item's key.
+  pub unknown_gifts: Vec<(RoomId, String)>,
+
+  // Rooms that teleport the player around in circles forever.
+  pub endless_teleports: Vec<RoomId>,
+}
+
+impl MapReport {
+  // Returns a human-readable line for each problem in the report.
+  pub fn describe(&self, map: &Map) -> Vec<String> {
+    let key = |room_id: RoomId| map.room(room_id).key.as_str();
+    let mut lines = Vec::new();
+
+    if !self.is_exit_reachable {
+      let exits: Vec<&str> = map.exits().map(key).collect();
+      lines.push(format!("The exit ({}) can't be reached from the \
+                          entrance.", exits.join(", ")));
+    }
+    for &room_id in self.unreachable_rooms.iter() {
+      lines.push(format!("{} can't be reached from the entrance.",
+                         key(room_id)));
+    }
+    for &room_id in self.dead_ends.iter() {
+      lines.push(format!("{} is a dead end.", key(room_id)));
+    }
+    for mismatch in self.description_mismatches.iter() {
+      lines.push(match *mismatch {
+        DescriptionMismatch::MissingExit(room_id, dir) => {
+          format!("{} mentions an exit to the {}, but there isn't one.",
+                  key(room_id), dir)
+        },
+        DescriptionMismatch::UndescribedExit(room_id, dir) => {
+          format!("{} has an exit {}, but its description doesn't \
+                   mention it.", key(room_id), dir.toward())
+        },
+        DescriptionMismatch::MissingStairs(room_id) => {
+          format!("{} mentions stairs, but there's no way up or down.",
+                  key(room_id))
+        },
+      });
+    }
+    for &(room_id, dir) in self.unopenable_locks.iter() {
+      lines.push(format!("{} has a lock {}, but the shop doesn't sell its \
+                          key.", key(room_id), dir.toward()));
+    }
+    for &(room_id, ref item) in self.unknown_gifts.iter() {
+      lines.push(format!("{} gives the player {}, but the shop doesn't \
+                          sell it.", key(room_id), item));
+    }
+    for &room_id in self.endless_teleports.iter() {
+      lines.push(format!("Entering {} teleports the player around in \
+                          circles.", key(room_id)));
+    }
+    lines
+  }
+}
+
+// Rooms that the player never actually stands in, because entering
+// them either ends the game or takes the player somewhere else.
+fn is_transient(map: &Map, room_id: RoomId) -> bool {
+  map.is_exit(room_id) || map.teleport_destination(room_id).is_some()
+}
+
+fn reachable_rooms(map: &Map) -> Vec<bool> {
+  let mut reached = vec![false; map.num_rooms()];
+  let mut queue = VecDeque::new();
+
+  reached[map.entrance.0] = true;
+  queue.push_back(map.entrance);
+
+  while let Some(room_id) = queue.pop_front() {
+    let next_rooms: Vec<RoomId> = if map.is_exit(room_id) {
+      vec![]
+    } else if let Some(destination) = map.teleport_destination(room_id) {
+      vec![destination]
+    } else {
+      // Locked doors and secret passages don't stop the player for
+      // long, since keys can always be bought and rooms searched.
+      Direction::iter().filter_map(|dir| {
+        map.room(room_id).exit(dir).map(|exit| exit.to)
+      }).collect()
+    };
+    for next in next_rooms {
+      if !reached[next.0] {
+        reached[next.0] = true;
+        queue.push_back(next);
+      }
+    }
+  }
+
+  reached
+}
+
+fn words(sentence: &str) -> Vec<String> {
+  sentence.split(|c: char| !c.is_alphabetic())
+    .filter(|word| !word.is_empty())
+    .map(|word| word.to_lowercase())
+    .collect()
+}
+
+fn check_description(map: &Map, room_id: RoomId,
+                     mismatches: &mut Vec<DescriptionMismatch>) {
+  let room = map.room(room_id);
+  let mut described = vec![false; Direction::size()];
+  let mut mentions_exits = false;
+  let mut mentions_stairs = false;
+
+  for sentence in room.description.split('.') {
+    let words = words(sentence);
+    let has_word = |list: &[&str]| {
+      words.iter().any(|word| list.contains(&word.as_str()))
+    };
+    if has_word(&STAIR_WORDS) {
+      mentions_stairs = true;
+    }
+    if has_word(&EXIT_WORDS) {
+      mentions_exits = true;
+      for dir in Direction::iter().filter(|dir| !dir.is_vertical()) {
+        if words.contains(&dir.to_string()) {
+          described[dir as usize] = true;
+        }
+      }
+    }
+  }
+
+  if !mentions_exits && !mentions_stairs {
+    // Some descriptions, like the entrance's, don't talk about
+    // exits at all, which is fine.
+    return;
+  }
+
+  let has_stairs = room.get_exit(Up).is_some() ||
+                   room.get_exit(Down).is_some();
+  if mentions_stairs && !has_stairs {
+    mismatches.push(DescriptionMismatch::MissingStairs(room_id));
+  }
+
+  for dir in Direction::iter() {
+    let exists = room.get_exit(dir).is_some();
+    let is_described = if dir.is_vertical() {
+      mentions_stairs
+    } else {
+      described[dir as usize]
+    };
+    if is_described && !exists && !dir.is_vertical() {
+      mismatches.push(DescriptionMismatch::MissingExit(room_id, dir));
+    } else if exists && !is_described {
+      mismatches.push(DescriptionMismatch::UndescribedExit(room_id, dir));
+    }
+  }
+}
+
+impl Map {
+  // Rooms where, after following every teleport from one room to the
+  // next, the player never actually arrives anywhere.
+  pub fn endless_teleports(&self) -> Vec<RoomId> {
+    self.room_ids().filter(|&start| {
+      let mut room_id = start;
+      for _ in 0..self.num_rooms() {
+        match self.teleport_destination(room_id) {
+          Some(destination) => { room_id = destination },
+          None => { return false },
+        }
+      }
+      true
+    }).collect()
+  }
+
+  pub fn validate(&self) -> MapReport {
+    let reached = reachable_rooms(self);
+    let mut report = MapReport {
+      unreachable_rooms: Vec::new(),
+      is_exit_reachable: self.exits().any(|r| reached[r.0]),
+      dead_ends: Vec::new(),
+      description_mismatches: Vec::new(),
+      unopenable_locks: Vec::new(),
+      unknown_gifts: Vec::new(),
+      endless_teleports: self.endless_teleports(),
+    };
+
+    for room_id in self.room_ids() {
+      if !reached[room_id.0] {
+        report.unreachable_rooms.push(room_id);
+      }
+      for dir in Direction::iter() {
+        let lock = self.room(room_id).exit(dir).and_then(|exit| {
+          exit.lock.as_ref()
+        });
+        if lock.is_some_and(|key| self.catalogue.find(key).is_none()) {
+          report.unopenable_locks.push((room_id, dir));
+        }
+      }
+      for trigger in self.room(room_id).triggers.iter() {
+        if let TriggerAction::Give(ref item, _) = trigger.action {
+          if self.catalogue.find(item).is_none() {
+            report.unknown_gifts.push((room_id, item.clone()));
+          }
+        }
+      }
+      if is_transient(self, room_id) {
+        continue;
+      }
+      let num_exits = Direction::iter().filter(|&dir| {
+        self.room(room_id).exit(dir).is_some()
+      }).count();
+      if num_exits <= 1 {
+        report.dead_ends.push(room_id);
+      }
+      check_description(self, room_id, &mut report.description_mismatches);
+    }
+
+    report
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use map::Map;
+  use direction::Direction::*;
+  use triggers::Trigger;
+  use triggers::TriggerAction::Teleport;
+  use super::DescriptionMismatch::*;
+
+  #[test]
+  fn test_castle_is_solvable() {
+    let map = Map::castle();
+    let report = map.validate();
+    let room = |key| map.find_room(key).unwrap();
+
+    assert!(report.is_exit_reachable);
+    assert_eq!(report.unreachable_rooms, vec![]);
+    assert_eq!(report.unopenable_locks, vec![]);
+    assert_eq!(report.unknown_gifts, vec![]);
+    assert_eq!(report.endless_teleports, vec![]);
+    assert_eq!(report.dead_ends, vec![
+      room("PrivateMeeting"), room("Entrance"), room("Kitchen"),
+      room("Dungeon"), room("DressingChamber"),
+    ]);
+    assert_eq!(report.description_mismatches, vec![
+      UndescribedExit(room("Hallway"), West),
+    ]);
+  }
+
+  #[test]
+  fn test_problems_are_found() {
+    let map = Map::parse("
+room Porch
+description There is a door to the north.
+room Attic
+description A stairwell leads nowhere.
+room Cellar
+connect Porch east Cellar
+lock Porch east crowbar
+on Cellar once give crowbar
+entrance Porch
+exit Attic
+").unwrap();
+    let report = map.validate();
+    let room = |key| map.find_room(key).unwrap();
+
+    assert!(!report.is_exit_reachable);
+    assert_eq!(report.unreachable_rooms, vec![room("Attic")]);
+    assert_eq!(report.description_mismatches, vec![
+      MissingExit(room("Porch"), North),
+      UndescribedExit(room("Porch"), East),
+    ]);
+    assert_eq!(report.unopenable_locks, vec![
+      (room("Porch"), East), (room("Cellar"), West),
+    ]);
+    assert_eq!(report.unknown_gifts, vec![
+      (room("Cellar"), String::from("crowbar")),
+    ]);
+    assert_eq!(report.describe(&map)[0],
+               "The exit (Attic) can't be reached from the entrance.");
+  }
+
+  #[test]
+  fn test_endless_teleports_are_found() {
+    let mut map = Map::castle();
+    let room = |key| map.find_room(key).unwrap();
+    let (hallway, lift, vestibule) =
+      (room("Hallway"), room("Lift"), room("RearVestibule"));
+    map.add_trigger(vestibule, Trigger::new(Teleport(lift)));
+    map.add_trigger(hallway, Trigger::new(Teleport(vestibule)));
+
+    let report = map.validate();
+    assert_eq!(report.endless_teleports, vec![hallway, lift, vestibule]);
+    assert!(report.describe(&map).contains(&String::from(
+      "Entering Hallway teleports the player around in circles.")));
+  }
+}
@@ -0,0 +1,217 @@
+use map_file::logical_lines;
+use monsters::{Bestiary, Monster, MonsterId};
+
+// Monsters are described in the same line-based text format as maps,
+// e.g.:
+//
+//   # How many rooms start with a monster in them.
+//   lairs 4
+//
+//   monster dragon
+//   name devastating ice-dragon
+//   ferocity 20
+//   description Frost creeps across the walls as it unfurls its
+//     enormous wings.
+//
+// The directives are:
+//
+//   lairs <number>        - how many rooms start with a monster
+//   monster <key>         - start describing a new monster
+//   name <text>           - what the monster is called
+//   article <word>        - the article to use before its name, e.g.
+//                           "an" (defaults to "a")
+//   description <text>    - shown when the player encounters it
+//   ferocity <number>     - how dangerous it is (defaults to 10)
+//   damage <number>       - how much strength the player loses when
+//                           it wounds them (defaults to 5)
+//   flee <percent>        - the player's chance of running away from
+//                           it (defaults to 30)
+//   loot <min> <max>      - how much treasure it leaves behind when
+//                           it's defeated (defaults to none)
+//   wander <percent>      - its chance of moving to a neighbouring
+//                           room each turn (defaults to 0); it always
+//                           moves towards the player if it can
+//   noise <text>          - what the player hears from the next room,
+//                           e.g. "growling" (defaults to nothing)
+//
+// Monster keys can't contain whitespace. Every room that's allotted a
+// monster gets one of them at random.
+
+struct MonsterParser {
+  bestiary: Bestiary,
+  curr_monster: Option<MonsterId>,
+}
+
+fn parse_number<T: ::std::str::FromStr>(directive: &str,
+                                        value: &str) -> Result<T, String> {
+  value.parse::<T>().map_err(|_| {
+    format!("Invalid number for '{}': {}", directive, value)
+  })
+}
+
+impl MonsterParser {
+  fn curr_monster(&mut self, directive: &str) -> Result<&mut Monster, String> {
+    match self.curr_monster {
+      Some(id) => Ok(self.bestiary.get_mut(id)),
+      None => Err(format!("'{}' must come after a 'monster' directive",
+                          directive)),
+    }
+  }
+
+  fn parse_directive(&mut self, directive: &str,
+                     rest: &str) -> Result<(), String> {
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let expect_args = |n: usize| {
+      if args.len() == n {
+        Ok(())
+      } else {
+        Err(format!("'{}' expects {} argument{}", directive, n,
+                    if n == 1 { "" } else { "s" }))
+      }
+    };
+
+    match directive {
+      "lairs" => {
+        expect_args(1)?;
+        self.bestiary.lairs = parse_number(directive, args[0])?;
+      },
+      "monster" => {
+        expect_args(1)?;
+        if self.bestiary.find(args[0]).is_some() {
+          return Err(format!("Monster {} is declared twice", args[0]));
+        }
+        self.curr_monster = Some(self.bestiary.add(Monster::new(args[0])));
+      },
+      "name" => {
+        self.curr_monster(directive)?.name = String::from(rest);
+      },
+      "article" => {
+        expect_args(1)?;
+        self.curr_monster(directive)?.article = String::from(rest);
+      },
+      "description" => {
+        self.curr_monster(directive)?.description = String::from(rest);
+      },
+      "ferocity" => {
+        expect_args(1)?;
+        let ferocity = parse_number(directive, args[0])?;
+        self.curr_monster(directive)?.ferocity = ferocity;
+      },
+      "damage" => {
+        expect_args(1)?;
+        let damage = parse_number(directive, args[0])?;
+        self.curr_monster(directive)?.damage = damage;
+      },
+      "flee" => {
+        expect_args(1)?;
+        let percent: u8 = parse_number(directive, args[0])?;
+        if percent > 100 {
+          return Err(format!("Invalid percentage: {}", percent));
+        }
+        self.curr_monster(directive)?.flee_chance = percent as f32 / 100.0;
+      },
+      "wander" => {
+        expect_args(1)?;
+        let percent: u8 = parse_number(directive, args[0])?;
+        if percent > 100 {
+          return Err(format!("Invalid percentage: {}", percent));
+        }
+        self.curr_monster(directive)?.wander_chance = percent as f32 / 100.0;
+      },
+      "noise" => {
+        self.curr_monster(directive)?.noise = String::from(rest);
+      },
+      "loot" => {
+        expect_args(2)?;
+        let min: u8 = parse_number(directive, args[0])?;
+        let max: u8 = parse_number(directive, args[1])?;
+        if min > max {
+          return Err(format!("Invalid loot range: {} to {}", min, max));
+        }
+        self.curr_monster(directive)?.loot = (min, max);
+      },
+      _ => { return Err(format!("Unknown directive: {}", directive)); }
+    }
+    Ok(())
+  }
+}
+
+impl Bestiary {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut parser = MonsterParser {
+      bestiary: Bestiary::new(),
+      curr_monster: None,
+    };
+
+    for (line_number, line) in logical_lines(data) {
+      let (directive, rest) = match line.find(' ') {
+        Some(i) => (&line[..i], line[i + 1..].trim()),
+        None => (line.as_str(), ""),
+      };
+      parser.parse_directive(directive, rest).map_err(|message| {
+        format!("Line {}: {}", line_number, message)
+      })?;
+    }
+
+    if parser.bestiary.is_empty() {
+      return Err(String::from("There are no monsters."));
+    }
+
+    Ok(parser.bestiary)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use monsters::{Bestiary, MonsterId};
+
+  const TINY_BESTIARY: &str = "
+    lairs 2
+    monster imp
+    article an
+    name imp
+    description A small,
+      annoying imp.
+    ferocity 3
+    flee 50
+    loot 1 5
+    wander 25
+    noise giggling
+  ";
+
+  #[test]
+  fn test_parse_tiny_bestiary() {
+    let data = TINY_BESTIARY.replace("\n    ", "\n");
+    let bestiary = Bestiary::parse(&data).unwrap();
+    assert_eq!(bestiary.lairs, 2);
+    assert_eq!(bestiary.find("imp"), Some(MonsterId(0)));
+
+    let imp = bestiary.get(MonsterId(0));
+    assert_eq!(imp.with_article(), "an imp");
+    assert_eq!(imp.description, "A small, annoying imp.");
+    assert_eq!(imp.ferocity, 3);
+    assert_eq!(imp.damage, 5);
+    assert_eq!(imp.flee_chance, 0.5);
+    assert_eq!(imp.loot, (1, 5));
+    assert_eq!(imp.wander_chance, 0.25);
+    assert_eq!(imp.noise, "giggling");
+
+    assert_eq!(Bestiary::parse(&data.replace("loot 1 5", "loot 5 1")).err(),
+               Some(String::from("Line 10: Invalid loot range: 5 to 1")));
+    assert_eq!(Bestiary::parse(&data.replace("wander 25", "wander 250")).err(),
+               Some(String::from("Line 11: Invalid percentage: 250")));
+    assert_eq!(Bestiary::parse("lairs 2").err(),
+               Some(String::from("There are no monsters.")));
+    assert!(Bestiary::parse("name imp").is_err());
+  }
+
+  #[test]
+  fn test_classic_monsters_are_valid() {
+    let bestiary = Bestiary::classic();
+    assert_eq!(bestiary.len(), 4);
+    assert_eq!(bestiary.lairs, 4);
+    let dragon = bestiary.find("dragon").unwrap();
+    assert_eq!(bestiary.get(dragon).ferocity, 20);
+    assert_eq!(bestiary.get(dragon).name, "devastating ice-dragon");
+  }
+}
@@ -1,46 +1,135 @@
 use std::fmt;
-use sized_enum::SizedEnum;
 
-use self::MonsterId::*;
+use rng::Rng;
 
-const NUM_MONSTERS: usize = 4;
+// The monsters from the book, which are used unless the player asks
+// for different ones. See `monster_file` for details on the format.
+const CLASSIC_MONSTERS: &str = include_str!("../maps/monsters.txt");
 
-enum_from_primitive! {
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub enum MonsterId {
-  Werewolf = 0,
-  Fleshgorger = 1,
-  Maldemer = 2,
-  Dragon = 3,
-}
+// How many rooms have a monster in them at the start of the game,
+// unless the bestiary says otherwise.
+pub const DEFAULT_LAIRS: usize = 4;
+
+// Monsters are identified by the order in which they were added to the
+// bestiary.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MonsterId(pub usize);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Monster {
+  pub key: String,
+  pub name: String,
+  pub article: String,
+  pub description: String,
+
+  // How dangerous the monster is to fight, before taking the player's
+  // equipment into account.
+  pub ferocity: i32,
+
+  // How much strength the player loses whenever the monster wounds
+  // them.
+  pub damage: i32,
+
+  // The chance that the player manages to run away from the monster.
+  pub flee_chance: f32,
+
+  // The smallest and largest amount of treasure the monster leaves
+  // behind when it's defeated.
+  pub loot: (u8, u8),
+
+  // The chance that the monster moves to a neighbouring room whenever
+  // the player takes a turn.
+  pub wander_chance: f32,
+
+  // What the player hears when the monster is in a neighbouring room,
+  // e.g. "growling". Monsters without a noise are silent.
+  pub noise: String,
 }
 
-impl MonsterId {
-  pub fn as_str(&self) -> &'static str {
-    match *self {
-      Werewolf => "ferocious werewolf",
-      Fleshgorger => "fanatical fleshgorger",
-      Maldemer => "maloventy maldemer",
-      Dragon => "devastating ice-dragon",
+impl Monster {
+  pub fn new(key: &str) -> Self {
+    Self {
+      key: String::from(key),
+      name: String::from(key),
+      article: String::from("a"),
+      description: String::new(),
+      ferocity: 10,
+      damage: 5,
+      flee_chance: 0.3,
+      loot: (0, 0),
+      wander_chance: 0.0,
+      noise: String::new(),
     }
   }
 
-  pub fn ferocity_factor(&self) -> i32 {
-    match *self {
-      Werewolf => 5,
-      Fleshgorger => 10,
-      Maldemer => 15,
-      Dragon => 20,
+  // The monster's name with its article, e.g. "an ice-dragon".
+  pub fn with_article(&self) -> String {
+    if self.article.is_empty() {
+      self.name.clone()
+    } else {
+      format!("{} {}", self.article, self.name)
     }
   }
 }
 
-impl SizedEnum for MonsterId {
-  fn size() -> usize { NUM_MONSTERS }
+impl fmt::Display for Monster {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name)
+  }
 }
 
-impl fmt::Display for MonsterId {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.as_str())
+// All the kinds of monster that can appear in the castle.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bestiary {
+  monsters: Vec<Monster>,
+  pub lairs: usize,
+}
+
+impl Bestiary {
+  pub fn new() -> Self {
+    Self { monsters: Vec::new(), lairs: DEFAULT_LAIRS }
+  }
+
+  pub fn classic() -> Self {
+    Self::parse(CLASSIC_MONSTERS).expect("The built-in monsters are invalid")
+  }
+
+  pub fn add(&mut self, monster: Monster) -> MonsterId {
+    self.monsters.push(monster);
+    MonsterId(self.monsters.len() - 1)
+  }
+
+  pub fn get(&self, id: MonsterId) -> &Monster {
+    &self.monsters[id.0]
+  }
+
+  pub fn get_mut(&mut self, id: MonsterId) -> &mut Monster {
+    &mut self.monsters[id.0]
+  }
+
+  pub fn len(&self) -> usize {
+    self.monsters.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.monsters.is_empty()
+  }
+
+  pub fn ids(&self) -> impl Iterator<Item = MonsterId> {
+    (0..self.monsters.len()).map(MonsterId)
+  }
+
+  pub fn find(&self, key: &str) -> Option<MonsterId> {
+    self.monsters.iter().position(|m| m.key == key).map(MonsterId)
+  }
+
+  pub fn random(&self, rng: &mut Rng) -> MonsterId {
+    MonsterId(rng.random_i32(0, self.monsters.len() as i32) as usize)
+  }
+}
+
+impl Default for Bestiary {
+  fn default() -> Self {
+    Self::new()
   }
 }
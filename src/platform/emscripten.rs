@@ -3,10 +3,9 @@
 
 use std::ffi::{CString, CStr};
 use std::cell::RefCell;
-use std::ptr::null_mut;
-use std::os::raw::{c_int, c_void, c_float, c_char};
+use std::os::raw::{c_int, c_float, c_char};
 
-use platform::AbstractPlatform;
+use platform::{AbstractPlatform, ProcessPlatform};
 
 const FPS: c_int = 1;
 
@@ -22,7 +21,8 @@ extern {
   fn emscripten_run_script_string(script: *const c_char) -> *const c_char;
 }
 
-thread_local!(static MAIN_LOOP_CALLBACK: RefCell<*mut c_void> = RefCell::new(null_mut()));
+thread_local!(static MAIN_LOOP_CALLBACK: RefCell<Option<Box<dyn FnMut()>>> =
+  RefCell::new(None));
 
 fn run_script(script: &str) {
   unsafe {
@@ -45,15 +45,27 @@ fn run_script_string(script: &str) -> String {
   }
 }
 
+// Runs the main loop callback. Besides being called by Emscripten's
+// main loop, this is called directly from JavaScript whenever the
+// player enters some input, so that the game can respond right away.
+#[no_mangle]
+pub extern "C" fn game_state_tick() {
+  MAIN_LOOP_CALLBACK.with(|callback| {
+    if let Some(ref mut callback) = *callback.borrow_mut() {
+      callback();
+    }
+  });
+}
+
 pub struct EmscriptenPlatform;
 
 impl AbstractPlatform for EmscriptenPlatform {
-  fn show_prompt(prompt: &str) {
+  fn show_prompt(&mut self, prompt: &str) {
     let script = format!("set_prompt({:?});", prompt);
     run_script(script.as_str());
   }
 
-  fn read_input() -> Option<String> {
+  fn read_input(&mut self) -> Option<String> {
     if run_script_int("has_input()") == 0 {
       None
     } else {
@@ -61,24 +73,51 @@ impl AbstractPlatform for EmscriptenPlatform {
     }
   }
 
-  fn sleep(ms: u64) {
+  fn sleep(&mut self, ms: u64) {
     let script = format!("sleep({});", ms);
     run_script(script.as_str());
   }
 
-  fn random() -> f32 {
-    unsafe {
-      emscripten_random()
+  fn clear_screen(&mut self) {
+    run_script("clear_screen()");
+  }
+
+  fn save_data(&mut self, key: &str, data: &str) -> Result<(), String> {
+    let script = format!("save_data({:?}, {:?})", key, data);
+    if run_script_int(script.as_str()) == 0 {
+      Err(String::from("Your browser refused to store the data."))
+    } else {
+      Ok(())
     }
   }
 
-  fn clear_screen() {
-    run_script("clear_screen()");
+  fn load_data(&mut self, key: &str) -> Option<String> {
+    let script = format!("has_data({:?})", key);
+    if run_script_int(script.as_str()) == 0 {
+      None
+    } else {
+      Some(run_script_string(format!("load_data({:?})", key).as_str()))
+    }
+  }
+
+  fn today(&self) -> Option<String> {
+    Some(run_script_string("new Date().toISOString().slice(0, 10)"))
   }
 
-  fn writeln_with_wrapping<T: AsRef<str>>(s: T) {
+  fn writeln_with_wrapping(&mut self, s: &str) {
     // The browser will take care of line-wrapping for us.
-    println!("{}", s.as_ref())
+    println!("{}", s)
+  }
+}
+
+impl ProcessPlatform for EmscriptenPlatform {
+  fn new() -> Self {
+    EmscriptenPlatform
+  }
+
+  fn random_seed() -> u64 {
+    let r = unsafe { emscripten_random() };
+    (r * u32::max_value() as c_float) as u64
   }
 
   fn terminate_program() {
@@ -86,18 +125,11 @@ impl AbstractPlatform for EmscriptenPlatform {
     unsafe { emscripten_cancel_main_loop() }
   }
 
-  fn set_main_loop_callback<F>(callback: F) where F: FnMut() {
-    MAIN_LOOP_CALLBACK.with(|log| {
-      *log.borrow_mut() = &callback as *const _ as *mut c_void;
+  fn set_main_loop_callback<F>(callback: F) where F: 'static + FnMut() {
+    MAIN_LOOP_CALLBACK.with(|main_loop_callback| {
+      *main_loop_callback.borrow_mut() = Some(Box::new(callback));
     });
 
-    unsafe { emscripten_set_main_loop(wrapper::<F>, FPS, 1); }
-
-    unsafe extern "C" fn wrapper<F>() where F: FnMut() {
-      MAIN_LOOP_CALLBACK.with(|z| {
-        let closure = *z.borrow_mut() as *mut F;
-        (*closure)();
-      });
-    }
+    unsafe { emscripten_set_main_loop(game_state_tick, FPS, 1); }
   }
 }
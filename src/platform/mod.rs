@@ -1,57 +1,100 @@
-#[cfg(target_os = "emscripten")]
-mod emscripten;
-
-#[cfg(not(target_os = "emscripten"))]
-mod stdio;
-
+// Writes a line of text to anything with a `writeln_with_wrapping()`
+// method, which is usually a `GameState` or a platform, e.g.:
+//
+//   wrapln!(state, "You have ${}.", state.wealth);
 macro_rules! wrapln {
-  ( ) => {{
-    use ::platform::*;
-    Platform::writeln_with_wrapping("");
+  ( $out:expr ) => {{
+    #[allow(unused_imports)]
+    use ::platform::AbstractPlatform;
+    $out.writeln_with_wrapping("");
   }};
-  ( $fmt:expr ) => {{
-    use ::platform::*;
-    Platform::writeln_with_wrapping($fmt);
+  ( $out:expr, $fmt:expr ) => {{
+    #[allow(unused_imports)]
+    use ::platform::AbstractPlatform;
+    let s = format!($fmt);
+    $out.writeln_with_wrapping(&s);
   }};
-  ( $fmt:expr, $($arg:tt)* ) => {{
-    use ::platform::*;
-    Platform::writeln_with_wrapping(format!($fmt, $($arg)*));
+  ( $out:expr, $fmt:expr, $($arg:tt)* ) => {{
+    #[allow(unused_imports)]
+    use ::platform::AbstractPlatform;
+    let s = format!($fmt, $($arg)*);
+    $out.writeln_with_wrapping(&s);
   }}
 }
 
+#[cfg(all(target_os = "emscripten", not(test)))]
+mod emscripten;
+
+#[cfg(all(not(target_os = "emscripten"), not(test)))]
+pub mod stdio;
+
+pub mod scripted;
+pub mod session;
+
+// Everything a single game needs in order to talk to the player. Each
+// game owns its own platform object, so several games can run in the
+// same process, each with its own input and output.
 pub trait AbstractPlatform {
-  fn init() {
+  fn show_prompt(&mut self, prompt: &str);
+
+  fn hide_prompt(&mut self) {
+    self.show_prompt("");
   }
 
-  fn show_prompt(prompt: &str);
+  fn read_input(&mut self) -> Option<String>;
 
-  fn hide_prompt() {
-    Self::show_prompt("");
+  // Returns whether there's no longer anyone on the other end, e.g.
+  // because the player hung up. Once this is true, no more input
+  // will ever arrive.
+  fn is_disconnected(&self) -> bool {
+    false
   }
 
-  fn read_input() -> Option<String>;
+  fn sleep(&mut self, ms: u64);
 
-  fn sleep(ms: u64);
+  fn clear_screen(&mut self);
 
-  fn random() -> f32;
+  // Persists a small piece of text data under the given key, e.g. a
+  // saved game, so that it can be retrieved in a future run.
+  fn save_data(&mut self, key: &str, data: &str) -> Result<(), String>;
 
-  fn random_i32(min: i32, max: i32) -> i32 {
-    let range = max - min;
+  fn load_data(&mut self, key: &str) -> Option<String>;
 
-    min + (Self::random() * range as f32) as i32
+  // Returns today's date in YYYY-MM-DD format, if the platform knows
+  // what it is.
+  fn today(&self) -> Option<String> {
+    None
   }
 
-  fn clear_screen();
+  fn writeln_with_wrapping(&mut self, s: &str);
+}
 
-  fn writeln_with_wrapping<T: AsRef<str>>(s: T);
+// The parts of a platform that apply to the whole program, rather
+// than to any one game.
+pub trait ProcessPlatform: AbstractPlatform + Sized {
+  fn init() {
+  }
+
+  // Returns the platform's usual way of talking to the player, e.g.
+  // the terminal.
+  fn new() -> Self;
+
+  // Returns a seed for the game's random number generator, for
+  // when the player hasn't asked for a specific one.
+  fn random_seed() -> u64;
 
   fn terminate_program();
 
-  fn set_main_loop_callback<F: FnMut()>(callback: F);
+  fn set_main_loop_callback<F: 'static + FnMut()>(callback: F);
 }
 
-#[cfg(target_os = "emscripten")]
+#[cfg(all(target_os = "emscripten", not(test)))]
 pub type Platform = emscripten::EmscriptenPlatform;
 
-#[cfg(not(target_os = "emscripten"))]
+#[cfg(all(not(target_os = "emscripten"), not(test)))]
 pub type Platform = stdio::StdioPlatform;
+
+// Unit tests always run headless, so that they can script the
+// player's input and inspect the game's output.
+#[cfg(test)]
+pub type Platform = scripted::ScriptedPlatform;
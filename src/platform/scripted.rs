@@ -0,0 +1,232 @@
+// A headless platform that reads its input from an in-memory queue
+// and records everything the game does into a transcript, so that
+// the game can be driven from tests or by other programs.
+//
+// Cloning a scripted platform gives another handle to the same script,
+// so a test can hand one copy to a game and keep the other around to
+// feed the game input and inspect its output.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use platform::{AbstractPlatform, ProcessPlatform};
+use game_state::GameState;
+
+#[cfg(test)]
+use map::Map;
+#[cfg(test)]
+use items::ItemId;
+
+const SCRIPTED_SEED: u64 = 1;
+
+pub const SCRIPTED_DATE: &str = "1980-01-01";
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TranscriptEntry {
+  Line(String),
+  Prompt(String),
+  Input(String),
+  ClearScreen,
+}
+
+struct ScriptState {
+  inputs: VecDeque<String>,
+  transcript: Vec<TranscriptEntry>,
+  data: HashMap<String, String>,
+}
+
+// Terminating the program is the one thing that affects the whole
+// process rather than a single script.
+thread_local!(static TERMINATED: Cell<bool> = const { Cell::new(false) });
+
+#[derive(Clone)]
+pub struct ScriptedPlatform {
+  script: Rc<RefCell<ScriptState>>,
+}
+
+impl ScriptedPlatform {
+  fn with_script<T, F: FnOnce(&mut ScriptState) -> T>(&self, f: F) -> T {
+    f(&mut self.script.borrow_mut())
+  }
+
+  pub fn push_input<T: AsRef<str>>(&self, line: T) {
+    self.with_script(|script| {
+      script.inputs.push_back(String::from(line.as_ref()))
+    });
+  }
+
+  pub fn push_inputs(&self, lines: &[&str]) {
+    for line in lines.iter() {
+      self.push_input(line);
+    }
+  }
+
+  pub fn has_input(&self) -> bool {
+    self.with_script(|script| !script.inputs.is_empty())
+  }
+
+  pub fn is_terminated() -> bool {
+    TERMINATED.with(|terminated| terminated.get())
+  }
+
+  pub fn transcript(&self) -> Vec<TranscriptEntry> {
+    self.with_script(|script| script.transcript.clone())
+  }
+
+  // Returns the transcript since it was last taken, and clears it.
+  pub fn take_transcript(&self) -> Vec<TranscriptEntry> {
+    self.with_script(|script| script.transcript.drain(..).collect())
+  }
+
+  // Returns everything written via `writeln_with_wrapping()` since
+  // the transcript was last taken, one line per call.
+  pub fn take_output(&self) -> String {
+    let mut output = String::new();
+    for entry in self.take_transcript() {
+      if let TranscriptEntry::Line(line) = entry {
+        output.push_str(line.as_str());
+        output.push('\n');
+      }
+    }
+    output
+  }
+
+  // Ticks the game until it either finishes or is waiting for input
+  // that hasn't been scripted yet.
+  pub fn run_until_blocked(&self, state: &mut GameState) {
+    while !state.is_finished() {
+      if state.is_waiting_for_input() && !self.has_input() {
+        return;
+      }
+      state.tick();
+    }
+  }
+}
+
+impl AbstractPlatform for ScriptedPlatform {
+  fn show_prompt(&mut self, prompt: &str) {
+    if !prompt.is_empty() {
+      self.with_script(|script| {
+        script.transcript.push(TranscriptEntry::Prompt(String::from(prompt)))
+      });
+    }
+  }
+
+  fn read_input(&mut self) -> Option<String> {
+    self.with_script(|script| {
+      let input = script.inputs.pop_front();
+      if let Some(ref line) = input {
+        script.transcript.push(TranscriptEntry::Input(line.clone()));
+      }
+      input
+    })
+  }
+
+  fn sleep(&mut self, _ms: u64) {
+  }
+
+  fn clear_screen(&mut self) {
+    self.with_script(|script| {
+      script.transcript.push(TranscriptEntry::ClearScreen)
+    });
+  }
+
+  fn save_data(&mut self, key: &str, data: &str) -> Result<(), String> {
+    self.with_script(|script| {
+      script.data.insert(String::from(key), String::from(data));
+      Ok(())
+    })
+  }
+
+  fn load_data(&mut self, key: &str) -> Option<String> {
+    self.with_script(|script| script.data.get(key).cloned())
+  }
+
+  fn today(&self) -> Option<String> {
+    Some(String::from(SCRIPTED_DATE))
+  }
+
+  fn writeln_with_wrapping(&mut self, s: &str) {
+    self.with_script(|script| {
+      script.transcript.push(TranscriptEntry::Line(String::from(s)))
+    });
+  }
+}
+
+impl ProcessPlatform for ScriptedPlatform {
+  fn new() -> Self {
+    Self {
+      script: Rc::new(RefCell::new(ScriptState {
+        inputs: VecDeque::new(),
+        transcript: Vec::new(),
+        data: HashMap::new(),
+      })),
+    }
+  }
+
+  fn random_seed() -> u64 {
+    SCRIPTED_SEED
+  }
+
+  fn terminate_program() {
+    TERMINATED.with(|terminated| terminated.set(true));
+  }
+
+  fn set_main_loop_callback<F>(mut callback: F) where F: 'static + FnMut() {
+    while !Self::is_terminated() {
+      callback();
+    }
+  }
+}
+
+// Starts a new game on a fully-connected map that contains no treasure
+// or monsters, so tests can place exactly what they need. Returns the
+// game along with a handle to its script.
+#[cfg(test)]
+pub fn new_empty_game() -> (GameState, ScriptedPlatform) {
+  let script = ScriptedPlatform::new();
+  let mut state = GameState::new(ScriptedPlatform::random_seed(), Map::castle(),
+                                 Box::new(script.clone()));
+  state.map.populate(&mut state.rng);
+  for room_id in state.map.room_ids().collect::<Vec<_>>() {
+    state.map.mut_room(room_id).contents = None;
+  }
+  (state, script)
+}
+
+// Gives the player some of the item with the given key from the shop's
+// catalogue, returning its id.
+#[cfg(test)]
+pub fn give_item(state: &mut GameState, key: &str, quantity: i32) -> ItemId {
+  let id = state.map.catalogue.find(key).expect("No such item");
+  state.items.set_quantity(id, quantity);
+  id
+}
+
+#[test]
+fn test_read_input_records_transcript() {
+  let mut platform = ScriptedPlatform::new();
+  platform.push_input("hi");
+  platform.show_prompt("> ");
+  assert_eq!(platform.read_input(), Some(String::from("hi")));
+  assert_eq!(platform.read_input(), None);
+  assert_eq!(platform.transcript(), vec![
+    TranscriptEntry::Prompt(String::from("> ")),
+    TranscriptEntry::Input(String::from("hi")),
+  ]);
+}
+
+#[test]
+fn test_scripts_are_independent() {
+  let (mut alice, alice_script) = new_empty_game();
+  let (mut bob, bob_script) = new_empty_game();
+  alice_script.push_inputs(&["Alice", "q"]);
+  bob_script.push_inputs(&["Bob"]);
+  alice_script.run_until_blocked(&mut alice);
+  bob_script.run_until_blocked(&mut bob);
+
+  assert!(alice_script.take_output().contains("Farewell."));
+  assert!(!bob_script.take_output().contains("Farewell."));
+  assert!(!bob.is_finished());
+}
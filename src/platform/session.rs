@@ -0,0 +1,174 @@
+// A platform that wraps another one in order to record or replay
+// sessions (see the `session` module for details). Every game's
+// platform is wrapped in one of these.
+//
+// When it's neither recording nor replaying, it just passes everything
+// through to the platform it wraps.
+
+use std::collections::{HashMap, VecDeque};
+
+use platform::AbstractPlatform;
+use session::Session;
+
+enum SessionState {
+  Idle,
+  Recording(Session),
+  Replaying(VecDeque<String>, Session),
+}
+
+pub struct SessionPlatform {
+  inner: Box<dyn AbstractPlatform>,
+  session: SessionState,
+
+  // Data saved while recording or replaying, which is thrown away
+  // along with the session.
+  session_data: HashMap<String, String>,
+}
+
+impl SessionPlatform {
+  pub fn new(inner: Box<dyn AbstractPlatform>) -> Self {
+    Self {
+      inner,
+      session: SessionState::Idle,
+      session_data: HashMap::new(),
+    }
+  }
+
+  fn is_replaying(&self) -> bool {
+    matches!(self.session, SessionState::Replaying(..))
+  }
+
+  // Starts recording into the given session, which should be empty.
+  pub fn start_recording(&mut self, session: Session) {
+    self.session_data.clear();
+    self.session = SessionState::Recording(session);
+  }
+
+  pub fn recorded_session(&self) -> Option<Session> {
+    match self.session {
+      SessionState::Recording(ref recorded) => Some(recorded.clone()),
+      _ => None,
+    }
+  }
+
+  pub fn stop_recording(&mut self) -> Option<Session> {
+    let recorded = self.recorded_session();
+    self.session = SessionState::Idle;
+    recorded
+  }
+
+  // While replaying, input comes from the given session rather than
+  // from the wrapped platform, and nothing is passed through to the
+  // wrapped platform: the game just runs silently, as fast as it can.
+  pub fn start_replay(&mut self, session: &Session) {
+    self.session_data.clear();
+    self.session = SessionState::Replaying(session.inputs().into_iter()
+                                             .collect(),
+                                           session.empty_copy());
+  }
+
+  pub fn has_replay_input(&self) -> bool {
+    match self.session {
+      SessionState::Replaying(ref inputs, _) => !inputs.is_empty(),
+      _ => false,
+    }
+  }
+
+  // Returns the session produced by the replay.
+  pub fn finish_replay(&mut self) -> Session {
+    match ::std::mem::replace(&mut self.session, SessionState::Idle) {
+      SessionState::Replaying(_, replayed) => replayed,
+      _ => panic!("No replay is in progress"),
+    }
+  }
+}
+
+impl AbstractPlatform for SessionPlatform {
+  fn show_prompt(&mut self, prompt: &str) {
+    if !self.is_replaying() {
+      self.inner.show_prompt(prompt);
+    }
+  }
+
+  fn read_input(&mut self) -> Option<String> {
+    if let SessionState::Replaying(ref mut inputs, ref mut replayed) =
+        self.session {
+      let input = inputs.pop_front();
+      if let Some(ref line) = input {
+        replayed.record_input(line);
+      }
+      return input;
+    }
+
+    let input = self.inner.read_input();
+    if let Some(ref line) = input {
+      if let SessionState::Recording(ref mut recorded) = self.session {
+        recorded.record_input(line);
+      }
+    }
+    input
+  }
+
+  fn is_disconnected(&self) -> bool {
+    !self.is_replaying() && self.inner.is_disconnected()
+  }
+
+  fn sleep(&mut self, ms: u64) {
+    if !self.is_replaying() {
+      self.inner.sleep(ms);
+    }
+  }
+
+  fn clear_screen(&mut self) {
+    if !self.is_replaying() {
+      self.inner.clear_screen();
+    }
+  }
+
+  // Sessions always start from scratch, so while recording or
+  // replaying, the game gets its own empty storage instead of the
+  // wrapped platform's. Otherwise a replay could behave differently
+  // depending on e.g. the high scores on the player's machine.
+  fn save_data(&mut self, key: &str, data: &str) -> Result<(), String> {
+    match self.session {
+      SessionState::Idle => self.inner.save_data(key, data),
+      _ => {
+        self.session_data.insert(String::from(key), String::from(data));
+        Ok(())
+      },
+    }
+  }
+
+  fn load_data(&mut self, key: &str) -> Option<String> {
+    match self.session {
+      SessionState::Idle => self.inner.load_data(key),
+      _ => self.session_data.get(key).cloned(),
+    }
+  }
+
+  // Sessions don't record the date they were played on, so it's
+  // unknown while recording or replaying.
+  fn today(&self) -> Option<String> {
+    match self.session {
+      SessionState::Idle => self.inner.today(),
+      _ => None,
+    }
+  }
+
+  fn writeln_with_wrapping(&mut self, s: &str) {
+    let passthrough = match self.session {
+      SessionState::Idle => true,
+      SessionState::Recording(ref mut recorded) => {
+        recorded.record_output(s);
+        true
+      },
+      SessionState::Replaying(_, ref mut replayed) => {
+        replayed.record_output(s);
+        false
+      },
+    };
+    if passthrough {
+      self.inner.writeln_with_wrapping(s);
+    }
+  }
+}
@@ -3,62 +3,156 @@ mod windows;
 
 mod word_wrap;
 
-use std::io::{self, Write};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use platform::AbstractPlatform;
+use platform::{AbstractPlatform, ProcessPlatform};
+use util;
 extern crate rand;
 
-pub struct StdioPlatform;
+// Normally the game talks to the terminal, but it can instead be
+// connected to any other source of input and destination for output,
+// such as a network socket.
+pub struct StdioPlatform {
+  input: Box<dyn BufRead>,
+  output: Box<dyn Write>,
+  is_closed: bool,
+  can_store_data: bool,
+}
 
-impl AbstractPlatform for StdioPlatform {
-  #[cfg(target_os = "windows")]
-  fn init() {
-    windows::enable_ansi();
+impl StdioPlatform {
+  // Data is stored in files in the current directory, which would be
+  // shared by everyone playing on the same machine, so connections
+  // don't get to store anything.
+  pub fn connect<R, W>(input: R, output: W) -> Self
+    where R: BufRead + 'static, W: Write + 'static
+  {
+    Self {
+      input: Box::new(input),
+      output: Box::new(output),
+      is_closed: false,
+      can_store_data: false,
+    }
   }
 
-  fn show_prompt(prompt: &str) {
-    io::stdout().write(prompt.as_bytes()).unwrap();
-    io::stdout().flush().unwrap();
+  // Passes our output to the given function. If the other end has
+  // gone away, the output is silently discarded.
+  fn write<F>(&mut self, f: F)
+    where F: FnOnce(&mut dyn Write) -> io::Result<()>
+  {
+    if self.is_closed {
+      return;
+    }
+    let output = &mut self.output;
+    if f(output).and_then(|_| output.flush()).is_err() {
+      self.is_closed = true;
+    }
   }
+}
 
-  fn read_input() -> Option<String> {
-    let mut input = String::new();
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
 
-    match io::stdin().read_line(&mut input) {
-      Ok(_) => { Some(String::from(input.trim())) },
+fn data_filename(key: &str) -> String {
+  format!("ww-{}.txt", key)
+}
+
+impl AbstractPlatform for StdioPlatform {
+  fn show_prompt(&mut self, prompt: &str) {
+    self.write(|output| output.write_all(prompt.as_bytes()));
+  }
+
+  // Input that isn't valid UTF-8 is decoded as best we can rather than
+  // thrown away, since otherwise we'd keep asking for input that never
+  // comes. Any other error means the connection is no use any more.
+  fn read_input(&mut self) -> Option<String> {
+    let mut input = Vec::new();
+
+    match self.input.read_until(b'\n', &mut input) {
+      Ok(0) => {
+        self.is_closed = true;
+        None
+      },
+      Ok(_) => {
+        Some(String::from(String::from_utf8_lossy(&input).trim()))
+      },
       Err(error) => {
-        println!("Error reading input: {}", error);
+        wrapln!(self, "Error reading input: {}", error);
+        self.is_closed = true;
         None
       },
     }
   }
 
-  fn sleep(ms: u64) {
+  fn is_disconnected(&self) -> bool {
+    self.is_closed
+  }
+
+  fn sleep(&mut self, ms: u64) {
     let dur = ::std::time::Duration::from_millis(ms);
     ::std::thread::sleep(dur);
   }
 
-  fn random() -> f32 {
-    rand::random::<f32>()
+  fn clear_screen(&mut self) {
+    self.write(|output| {
+      // Clear the screen.
+      write!(output, "{}[2J", 27 as char)?;
+
+      // Move the cursor to the home position.
+      write!(output, "{}[H", 27 as char)
+    });
+  }
+
+  fn save_data(&mut self, key: &str, data: &str) -> Result<(), String> {
+    if !self.can_store_data {
+      return Err(String::from("Saving isn't available over the network."));
+    }
+    fs::write(data_filename(key), data).map_err(|e| e.to_string())
+  }
+
+  fn load_data(&mut self, key: &str) -> Option<String> {
+    if !self.can_store_data {
+      return None;
+    }
+    fs::read_to_string(data_filename(key)).ok()
+  }
+
+  fn today(&self) -> Option<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    Some(util::date_from_days((now.as_secs() / SECONDS_PER_DAY) as i64))
   }
 
-  fn clear_screen() {
-    // Clear the screen.
-    print!("{}[2J", 27 as char);
+  fn writeln_with_wrapping(&mut self, s: &str) {
+    self.write(|output| word_wrap::writeln_with_wrapping(output, s));
+  }
+}
 
-    // Move the cursor to the home position.
-    print!("{}[H", 27 as char);
+impl ProcessPlatform for StdioPlatform {
+  #[cfg(target_os = "windows")]
+  fn init() {
+    windows::enable_ansi();
+  }
+
+  fn new() -> Self {
+    Self {
+      input: Box::new(BufReader::new(io::stdin())),
+      output: Box::new(io::stdout()),
+      is_closed: false,
+      can_store_data: true,
+    }
   }
 
-  fn writeln_with_wrapping<T: AsRef<str>>(s: T) {
-    word_wrap::writeln_with_wrapping(s.as_ref())
+  fn random_seed() -> u64 {
+    // Keep seeds short enough that players can easily read them
+    // out in bug reports.
+    rand::random::<u32>() as u64
   }
 
   fn terminate_program() {
     ::std::process::exit(0);
   }
 
-  fn set_main_loop_callback<F>(mut callback: F) where F: FnMut() {
+  fn set_main_loop_callback<F>(mut callback: F) where F: 'static + FnMut() {
     loop {
       callback();
     }
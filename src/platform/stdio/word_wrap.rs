@@ -9,9 +9,8 @@ const LF: u8 = 10;
 // ASCII, among other things. It's good enough for our needs
 // and much more lightweight than the current crates.io
 // alternative, `textwrap`, which has a lot of dependencies.
-pub fn writeln_with_wrapping(s: &str) {
-  let stdout = io::stdout();
-  let mut handle = stdout.lock();
+pub fn writeln_with_wrapping(handle: &mut dyn Write,
+                             s: &str) -> io::Result<()> {
   let bytes = s.as_bytes();
   let mut column = 0;
   let mut last_space = 0;
@@ -20,7 +19,7 @@ pub fn writeln_with_wrapping(s: &str) {
   for &c in bytes.iter() {
     i += 1;
     if c == SPACE || c == LF {
-      handle.write(&bytes[last_space..i]).unwrap();
+      handle.write_all(&bytes[last_space..i])?;
       if c == SPACE {
         column += i - last_space;
       } else {
@@ -33,14 +32,14 @@ pub fn writeln_with_wrapping(s: &str) {
         // Ack, we've got a really long word that exceeds the
         // length of a single line. Just write it out, breaking
         // it at the end of the line.
-        handle.write(&bytes[last_space..i]).unwrap();
+        handle.write_all(&bytes[last_space..i])?;
         last_space = i;
       }
-      handle.write(b"\n").unwrap();
+      handle.write_all(b"\n")?;
       column = 0;
     }
   }
 
-  handle.write(&bytes[last_space..i]).unwrap();
-  handle.write(b"\n").unwrap();
+  handle.write_all(&bytes[last_space..i])?;
+  handle.write_all(b"\n")
 }
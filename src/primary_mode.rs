@@ -1,9 +1,10 @@
-use map::{RoomId, RoomContents};
+use map::RoomContents;
 use sized_enum::SizedEnum;
 use direction::Direction;
 use game_state::{GameState, GameMode};
-use command::CommandInfo;
-use items::Item::*;
+use high_scores::Outcome;
+use events::GameEvent;
+use command::{CommandInfo, ParsedCommand};
 use platform::*;
 use util;
 
@@ -16,9 +17,14 @@ pub enum PrimaryCommand {
   Go(Direction),
   Inventory,
   Look,
+  Search,
+  Unlock,
   EatFood,
   MagicAmulet,
   PickUpTreasure,
+  ShowMap,
+  ShowHighScores,
+  SaveGame,
   Quit,
 
   #[cfg(debug_assertions)]
@@ -29,102 +35,143 @@ command_processor!(PrimaryCommand, {
   [
     Direction::iter().map(|dir| {
       CommandInfo::new(dir.character(), format!("go {}", dir), Go(dir))
+        .phrases(&[format!("go {}", dir), dir.to_string()])
     }).collect(),
     vec![
-      CommandInfo::new('c', "consume food", EatFood),
-      CommandInfo::new('m', "use magic amulet (if equipped)", MagicAmulet),
-      CommandInfo::new('i', "inventory/buy provisions", Inventory),
-      CommandInfo::new('p', "pick up treasure", PickUpTreasure),
-      CommandInfo::new('l', "look around", Look),
-      CommandInfo::new('q', "quit", Quit),
+      CommandInfo::new('c', "consume food", EatFood)
+        .phrases(&["eat food", "eat"]),
+      CommandInfo::new('m', "use magic amulet (if equipped)", MagicAmulet)
+        .phrases(&["use amulet", "use magic amulet", "amulet"]),
+      CommandInfo::new('i', "inventory/buy provisions", Inventory)
+        .phrases(&["inventory", "buy", "shop"]),
+      CommandInfo::new('p', "pick up treasure", PickUpTreasure)
+        .phrases(&["get treasure", "pick up treasure", "get", "pick up"]),
+      CommandInfo::new('l', "look around", Look)
+        .phrases(&["look", "look around"]),
+      CommandInfo::new('x', "search for secret passages", Search)
+        .phrases(&["search", "search room"]),
+      CommandInfo::new('k', "unlock doors", Unlock)
+        .phrases(&["unlock", "unlock door", "use key"]),
+      CommandInfo::new('a', "show a map of where you've been", ShowMap)
+        .phrases(&["map", "show map"]),
+      CommandInfo::new('r', "show high scores", ShowHighScores)
+        .phrases(&["high scores", "scores", "show high scores"]),
+      CommandInfo::new('v', "save game", SaveGame)
+        .phrases(&["save game", "save"]),
+      CommandInfo::new('q', "quit", Quit)
+        .phrases(&["quit"]),
 
       #[cfg(debug_assertions)]
-      CommandInfo::new('`', "debug mode", Debug).hidden(),
+      CommandInfo::new('`', "debug mode", Debug).hidden()
+        .phrases(&["debug"]),
     ],
   ].concat()
 });
 
+fn join_names(names: &[String]) -> String {
+  util::friendly_join(names.iter().map(|name| name.as_str()).collect())
+}
+
 impl GameState {
-  fn print_status_report(&self) {
+  fn print_status_report(&mut self) {
     if self.strength <= MIN_STRENGTH_WARNING {
-      wrapln!("Warning, {}! Your strength is running low.\n",
+      wrapln!(self, "Warning, {}! Your strength is running low.\n",
               self.player_name);
     }
-    wrapln!("{}, your strength is {}.", self.player_name, self.strength);
+    wrapln!(self, "{}, your strength is {}.", self.player_name, self.strength);
     self.print_wealth();
-    if self.items.get_quantity(Food) > 0 { self.print_food(); }
-    if self.items.owns(Armor) {
-      wrapln!("You are wearing armor.");
+    if self.food() > 0 { self.print_food(); }
+    let (worn, carried) = self.get_item_names();
+    if !worn.is_empty() {
+      wrapln!(self, "You are wearing {}.", join_names(&worn));
     }
-    let item_names = self.get_item_names();
-    if item_names.len() > 0 {
-      wrapln!("You are carrying {}.",
-              util::friendly_join(self.get_item_names()));
+    if !carried.is_empty() {
+      wrapln!(self, "You are carrying {}.", join_names(&carried));
     }
   }
 
-  fn get_item_names(&self) -> Vec<&str> {
-    let mut items = Vec::new();
-    for &item in [Axe, Sword, Amulet].iter() {
-      if self.items.owns(item) { items.push(item.as_str()) }
+  // The names of the armor the player is wearing, and of everything
+  // else they're carrying that's worth mentioning. Light sources and
+  // provisions go without saying.
+  fn get_item_names(&self) -> (Vec<String>, Vec<String>) {
+    let mut worn = Vec::new();
+    let mut carried = Vec::new();
+    for id in self.items.owned() {
+      let item = self.map.catalogue.get(id);
+      if item.armor.is_some() {
+        worn.push(item.name.clone());
+      } else if !item.light && !item.stackable {
+        carried.push(item.name.clone());
+      }
     }
-    items
+    (worn, carried)
   }
 
   fn use_amulet(&mut self) {
-    if self.items.owns(Amulet) {
-      wrapln!("You invoke the magic amulet and are whisked \
-               away to somewhere else...");
-      Self::pause();
+    let amulet = self.map.catalogue.teleporter();
+    if let Some(id) = amulet.filter(|&id| self.items.owns(id)) {
+      let name = self.map.catalogue.get(id).noun.clone();
+      wrapln!(self, "You invoke the magic {} and are whisked \
+               away to somewhere else...", name);
+      self.pause();
       loop {
-        let room_id = RoomId::random();
+        let room_id = self.map.random_room(&mut self.rng);
         if room_id != self.curr_room {
-          self.curr_room = room_id;
-          self.show_desc = true;
+          self.enter_room(room_id);
           self.process_move();
           break;
         }
       }
     } else {
-      wrapln!("You don't have the amulet, {}.", self.player_name);
+      wrapln!(self, "You don't have the amulet, {}.", self.player_name);
     }
   }
 
-  fn describe_room(&self) {
+  fn describe_room(&mut self) {
+    // Write straight to the platform, since the room is borrowed from
+    // the map while we describe it.
     let room = self.map.room(self.curr_room);
-    wrapln!("{}", room.description);
+    wrapln!(self.platform, "{}", room.description);
     if let Some(RoomContents::Treasure(amount)) = room.contents {
-      wrapln!("\nThere is treasure here worth ${}.", amount);
+      wrapln!(self.platform, "\nThere is treasure here worth ${}.", amount);
     }
   }
 
-  fn process_cmd(&mut self, cmd: PrimaryCommand) {
-    match cmd {
+  fn process_cmd(&mut self, parsed: ParsedCommand<PrimaryCommand>) {
+    match parsed.cmd {
       Go(dir) => { self.try_to_move(dir); },
       Inventory => { self.set_mode(GameMode::Inventory) },
       PickUpTreasure => {
         if !self.can_player_see() {
-          wrapln!("It's too dark to see any treasure here.");
+          wrapln!(self, "It's too dark to see any treasure here.");
         } else if let Some(RoomContents::Treasure(amt)) =
             self.map.room(self.curr_room).contents {
-          wrapln!("You are now ${} richer.", amt);
+          wrapln!(self, "You are now ${} richer.", amt);
           self.wealth += amt as i32;
           self.map.mut_room(self.curr_room).contents = None;
           self.process_move();
+          self.emit(GameEvent::TreasurePickedUp(amt as i32));
         } else {
-          wrapln!("There is no treasure to pick up here.");
+          wrapln!(self, "There is no treasure to pick up here.");
         }
       },
       Look => { self.show_desc = true },
+      Search => { self.search_room() },
+      Unlock => { self.unlock_doors() },
       EatFood => {
-        if !self.items.owns(Food) {
-          wrapln!("You have no food!");
+        if self.food() == 0 {
+          wrapln!(self, "You have no food!");
+        } else if let Some(amount) = parsed.amount {
+          self.eat_food(amount);
         } else {
           self.set_mode(GameMode::EatFood);
         }
       },
       MagicAmulet => { self.use_amulet() },
-      Quit => { self.finish_game() },
+      ShowMap => { self.show_automap() },
+      SaveGame => { self.save_game() },
+      ShowHighScores => { self.show_high_scores() },
+      Quit => { self.finish_game(Outcome::Quit) },
 
       #[cfg(debug_assertions)]
       Debug => { self.set_mode(GameMode::Debug) },
@@ -133,54 +180,173 @@ impl GameState {
 
   pub fn tick_primary_mode(&mut self) {
     if self.show_desc {
-      match self.curr_room {
-        RoomId::Lift => {
-          wrapln!("You have entered the lift...");
-          Self::pause();
-          wrapln!("It slowly descends...");
-          Self::pause();
-          self.curr_room = RoomId::RearVestibule;
+      let first_visit = !self.explored.has_visited(self.curr_room);
+      self.explored.visit(self.curr_room);
+      if self.pending_triggers {
+        self.pending_triggers = false;
+        if !self.fire_triggers(first_visit) {
           return;
-        },
-        RoomId::Exit => {
-          wrapln!("\nYou've done it!!");
-          Self::pause();
-          wrapln!("That was the exit from the castle.");
-          Self::pause();
-          wrapln!("\nYou have succeeded, {}!", self.player_name);
-          wrapln!("\nYou managed to get out of the castle.");
-          Self::pause();
-          wrapln!("\nWell done!");
-          Self::pause();
-          self.finish_game();
-          return;
-        },
-        _ => {
-          Platform::clear_screen();
-          self.print_status_report();
-          wrapln!();
-          if !self.can_player_see() {
-            wrapln!("It is too dark to see anything.");
-          } else {
-            self.describe_room();
-            if self.maybe_start_combat() {
-              return;
-            }
-          }
-          if !self.shown_hint {
-            wrapln!("\n(You can press 'h' for help at any time.)");
-            self.shown_hint = true;
-          }
-          wrapln!();
         }
       }
+      self.platform.clear_screen();
+      self.print_status_report();
+      wrapln!(self);
+      let ambushed = self.ambushed;
+      self.ambushed = false;
+      if !self.can_player_see() {
+        wrapln!(self, "It is too dark to see anything.");
+      } else {
+        self.describe_room();
+        self.explored.see_all_exits(&self.map, self.curr_room);
+      }
+      if (ambushed || self.can_player_see()) && self.maybe_start_combat() {
+        return;
+      }
+      self.listen_for_monsters();
+      if !self.shown_hint {
+        wrapln!(self, "\n(You can press 'h' for help at any time.)");
+        self.shown_hint = true;
+      }
+      wrapln!(self);
       self.show_desc = false;
     }
 
     self.ask("What do you want to do? ", |state, input| {
-      if let Some(cmd) = PrimaryCommand::get_from_input(input) {
-        state.process_cmd(cmd);
+      if let Some(parsed) = PrimaryCommand::get_from_input(state, input) {
+        state.process_cmd(parsed);
       };
     });
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use game_state::GameMode;
+  use command::ParserMode;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_moving_east_into_the_dark() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("Alice, your strength is 100."));
+    assert!(output.contains("It is too dark to see anything."));
+    assert_eq!(state.curr_room, state.map.find_room("Hallway").unwrap());
+    assert_eq!(state.strength, 95);
+    assert_eq!(state.tally, 1);
+  }
+
+  #[test]
+  fn test_torch_lights_the_way() {
+    let (mut state, script) = new_empty_game();
+    give_item(&mut state, "torch", 1);
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("You are in the hallway."));
+  }
+
+  #[test]
+  fn test_cannot_walk_through_walls() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "n"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("You can't go that way."));
+    assert_eq!(state.curr_room, state.map.entrance);
+  }
+
+  #[test]
+  fn test_help_lists_commands() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "h"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("  n - go north"));
+    assert!(output.contains("  q - quit"));
+    assert!(!output.contains("debug mode"));
+  }
+
+  #[test]
+  fn test_classic_parser_only_reads_first_letter() {
+    let (mut state, script) = new_empty_game();
+    give_item(&mut state, "food", 5);
+    script.push_inputs(&["Alice", "eat"]);
+    script.run_until_blocked(&mut state);
+
+    assert_eq!(state.curr_room, state.map.find_room("Hallway").unwrap());
+  }
+
+  #[test]
+  fn test_word_parser_understands_phrases() {
+    let (mut state, script) = new_empty_game();
+    state.parser = ParserMode::Words;
+    let food = give_item(&mut state, "food", 5);
+    script.push_inputs(&["Alice", "consume 3 food", "walk east"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("After some munching, you feel stronger."));
+    assert_eq!(state.items.get_quantity(food), 2);
+    assert_eq!(state.strength, 125);
+    assert_eq!(state.curr_room, state.map.find_room("Hallway").unwrap());
+  }
+
+  #[test]
+  fn test_word_parser_reports_ambiguity() {
+    let (mut state, script) = new_empty_game();
+    state.parser = ParserMode::Words;
+    script.push_inputs(&["Alice", "ea", "dance"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("Did you mean 'go east' or 'consume food'?"));
+    assert!(output.contains("I have no idea what you're talking about."));
+    assert_eq!(state.curr_room, state.map.entrance);
+  }
+
+  #[test]
+  fn test_picking_up_treasure() {
+    use map::RoomContents::Treasure;
+
+    let (mut state, script) = new_empty_game();
+    let entrance = state.map.entrance;
+    state.map.mut_room(entrance).contents = Some(Treasure(50));
+    script.push_inputs(&["Alice", "p"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("You are now $50 richer."));
+    assert_eq!(state.wealth, 125);
+    assert!(state.map.room(entrance).contents.is_none());
+  }
+
+  #[test]
+  fn test_showing_the_map() {
+    let (mut state, script) = new_empty_game();
+    give_item(&mut state, "torch", 1);
+    script.push_inputs(&["Alice", "e", "a"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("Ground floor:\n\n  [ 1]-< 2>\n"));
+    assert!(output.contains("   2 Hallway - you are here"));
+  }
+
+  #[test]
+  fn test_quitting_shows_score() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "q"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output()
+      .contains("Your score is 650."));
+    assert_eq!(state.curr_mode, GameMode::Finished);
+  }
+}
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+// This is the SplitMix64 generator. It's tiny, fast, and--most
+// importantly for us--produces exactly the same sequence for a given
+// seed on every platform, so a seed is all we need to reproduce a
+// castle layout or the outcome of a fight.
+const GOLDEN_GAMMA: u64 = 0x9E3779B97F4A7C15;
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+  seed: u64,
+  state: u64,
+  queued: VecDeque<f32>,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Self { seed, state: seed, queued: VecDeque::new() }
+  }
+
+  // Recreates a generator from the seed and state of another one, so
+  // that it continues exactly where that one left off.
+  pub fn restore(seed: u64, state: u64) -> Self {
+    Self { seed, state, queued: VecDeque::new() }
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  pub fn state(&self) -> u64 {
+    self.state
+  }
+
+  fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+  }
+
+  // Returns a number in the range [0, 1).
+  pub fn random(&mut self) -> f32 {
+    if let Some(value) = self.queued.pop_front() {
+      return value;
+    }
+    (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+  }
+
+  // Returns a number in the range [min, max).
+  pub fn random_i32(&mut self, min: i32, max: i32) -> i32 {
+    let range = max - min;
+
+    min + (self.random() * range as f32) as i32
+  }
+
+  // Makes the next calls to `random()` return the given values, before
+  // going back to the pseudo-random sequence. This is mostly useful
+  // for tests that need to force the outcome of a fight.
+  pub fn queue(&mut self, values: &[f32]) {
+    self.queued.extend(values.iter());
+  }
+}
+
+#[test]
+fn test_same_seed_gives_same_sequence() {
+  let mut a = Rng::new(1234);
+  let mut b = Rng::new(1234);
+  for _ in 0..100 {
+    assert_eq!(a.random_i32(0, 1000), b.random_i32(0, 1000));
+  }
+  assert_eq!(a.seed(), 1234);
+}
+
+#[test]
+fn test_random_is_in_range() {
+  let mut rng = Rng::new(5);
+  for _ in 0..1000 {
+    let r = rng.random();
+    assert!((0.0..1.0).contains(&r));
+  }
+}
+
+#[test]
+fn test_queued_values_come_first() {
+  let mut rng = Rng::new(5);
+  rng.queue(&[0.25, 0.75]);
+  assert_eq!(rng.random(), 0.25);
+  assert_eq!(rng.random(), 0.75);
+  assert_eq!(rng.random(), Rng::new(5).random());
+}
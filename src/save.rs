@@ -0,0 +1,485 @@
+use std::str::FromStr;
+
+use map::{Map, RoomId, RoomContents};
+use monsters::MonsterId;
+use direction::Direction;
+use rng::Rng;
+use achievements::Fact;
+use sized_enum::SizedEnum;
+use game_state::{GameState, GameMode};
+use platform::{AbstractPlatform, ProcessPlatform};
+use platform::scripted::ScriptedPlatform;
+
+pub const SAVE_KEY: &str = "savegame";
+
+// The first line of every saved game. The number at the end should be
+// bumped whenever the format changes in a way that older versions of
+// the game can't read.
+const SAVE_HEADER: &str = "werewolves-and-wanderer save 5";
+
+// A saved game is a plain text file with one "<field> <value>" pair
+// per line, e.g.:
+//
+//   werewolves-and-wanderer save 5
+//   map 15d207b1f4fafd18
+//   name Alice
+//   strength 85
+//   room 2
+//   items 1 0 0 3 0 0
+//   visited 0 2 5
+//   seen_exits 2 ns
+//   unlocked 4 e
+//   found 9 n
+//   facts bought_torch has_axe
+//   entered 0 2 5
+//   contents 7 treasure 50
+//   contents 12 terror 3
+//
+// Rooms, items and monsters are identified by their numeric ids. This
+// means that a game can only be resumed on the map it was saved on, so
+// the map's fingerprint is saved too.
+// Exits are identified by the same letters the player uses to go
+// through them. Only the doors the player has unlocked and the secret
+// passages they've found are saved; every other exit is restored the
+// way the map describes it.
+
+fn parse<T: FromStr>(field: &str, value: &str) -> Result<T, String> {
+  value.parse::<T>().map_err(|_| {
+    format!("Invalid value for '{}': {:?}", field, value)
+  })
+}
+
+fn parse_room_id(map: &Map, value: &str) -> Result<RoomId, String> {
+  let index: usize = parse("room", value)?;
+  if index < map.num_rooms() {
+    Ok(RoomId(index))
+  } else {
+    Err(format!("Unknown room id: {}", value))
+  }
+}
+
+fn parse_exits(map: &Map, field: &str,
+               args: &[&str]) -> Result<(RoomId, Vec<Direction>), String> {
+  if args.len() != 2 {
+    return Err(format!("Invalid value for '{}': {:?}", field,
+                       args.join(" ")));
+  }
+  let room_id = parse_room_id(map, args[0])?;
+  let dirs = args[1].chars().map(|c| {
+    Direction::iter().find(|dir| dir.character() == c).ok_or_else(|| {
+      format!("Unknown direction: {}", c)
+    })
+  }).collect::<Result<Vec<Direction>, String>>()?;
+  Ok((room_id, dirs))
+}
+
+// One "<field> <room> <dirs>" line for each room with any exits that
+// the predicate is true for.
+fn exit_lines<F>(map: &Map, field: &str, is_included: F) -> Vec<String>
+  where F: Fn(RoomId, Direction) -> bool
+{
+  map.room_ids().filter_map(|room_id| {
+    let dirs: String = Direction::iter().filter(|&dir| {
+      is_included(room_id, dir)
+    }).map(|dir| dir.character()).collect();
+    if dirs.is_empty() {
+      None
+    } else {
+      Some(format!("{} {} {}", field, room_id.0, dirs))
+    }
+  }).collect()
+}
+
+fn parse_contents(map: &Map,
+                  args: &[&str]) -> Result<(RoomId, RoomContents), String> {
+  if args.len() != 3 {
+    return Err(format!("Invalid room contents: {:?}", args.join(" ")));
+  }
+  let room_id = parse_room_id(map, args[0])?;
+  let contents = match args[1] {
+    "treasure" => RoomContents::Treasure(parse("treasure", args[2])?),
+    "terror" => {
+      let monster = MonsterId(parse("terror", args[2])?);
+      if monster.0 >= map.monsters.len() {
+        return Err(format!("Unknown monster id: {}", args[2]));
+      }
+      RoomContents::Terror(monster)
+    },
+    _ => { return Err(format!("Unknown room contents: {}", args[1])); }
+  };
+  Ok((room_id, contents))
+}
+
+impl GameState {
+  pub fn save_game(&mut self) {
+    let data = self.to_save_data();
+    match self.platform.save_data(SAVE_KEY, &data) {
+      Ok(()) => { wrapln!(self, "Your game has been saved.") },
+      Err(message) => {
+        wrapln!(self, "Unable to save your game: {}", message)
+      },
+    }
+  }
+
+  pub fn ask_to_resume(&mut self, data: String) {
+    self.ask("Would you like to resume your saved game? ",
+             move |state, input| {
+      let lower_input = input.to_lowercase();
+      if lower_input.starts_with('y') {
+        match state.restore_save_data(&data) {
+          Ok(()) => {
+            wrapln!(state, "Welcome back, {}.", state.player_name);
+            state.pause();
+            state.set_mode(GameMode::Primary);
+          },
+          Err(message) => {
+            wrapln!(state, "Unable to resume your saved game: {}", message);
+          },
+        }
+      } else if !lower_input.starts_with('n') {
+        wrapln!(state, "Please answer 'yes' or 'no'.");
+        state.ask_again();
+      }
+    });
+  }
+
+  pub fn to_save_data(&self) -> String {
+    let mut lines = vec![
+      String::from(SAVE_HEADER),
+      format!("map {:016x}", self.map.fingerprint()),
+      format!("name {}", self.player_name),
+      format!("seed {}", self.rng.seed()),
+      format!("rng_state {}", self.rng.state()),
+      format!("strength {}", self.strength),
+      format!("wealth {}", self.wealth),
+      format!("tally {}", self.tally),
+      format!("monsters_killed {}", self.monsters_killed),
+      format!("room {}", self.curr_room.0),
+      format!("shown_hint {}", self.shown_hint),
+    ];
+
+    let quantities: Vec<String> = self.map.catalogue.ids().map(|id| {
+      self.items.get_quantity(id).to_string()
+    }).collect();
+    lines.push(format!("items {}", quantities.join(" ")));
+
+    let visited: Vec<String> = self.explored.visited_rooms().map(|room_id| {
+      room_id.0.to_string()
+    }).collect();
+    lines.push(format!("visited {}", visited.join(" ")));
+
+    lines.extend(exit_lines(&self.map, "seen_exits", |room_id, dir| {
+      self.explored.has_seen_exit(room_id, dir)
+    }));
+    lines.extend(exit_lines(&self.map, "unlocked", |room_id, dir| {
+      self.map.room(room_id).exit(dir).is_some_and(|exit| {
+        exit.lock.is_some() && !exit.locked
+      })
+    }));
+    lines.extend(exit_lines(&self.map, "found", |room_id, dir| {
+      self.map.room(room_id).exit(dir).is_some_and(|exit| {
+        exit.secret && !exit.hidden
+      })
+    }));
+
+    let facts: Vec<&str> = self.achievements.facts().iter().map(|fact| {
+      fact.name()
+    }).collect();
+    lines.push(format!("facts {}", facts.join(" ")));
+
+    let entered: Vec<String> = self.achievements.entered_rooms()
+      .map(|room_id| room_id.0.to_string()).collect();
+    lines.push(format!("entered {}", entered.join(" ")));
+
+    for room_id in self.map.room_ids() {
+      match self.map.room(room_id).contents {
+        Some(RoomContents::Treasure(amount)) => {
+          lines.push(format!("contents {} treasure {}",
+                             room_id.0, amount));
+        },
+        Some(RoomContents::Terror(monster_id)) => {
+          lines.push(format!("contents {} terror {}",
+                             room_id.0, monster_id.0));
+        },
+        None => {}
+      }
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+  }
+
+  // Restores the game from the output of `to_save_data()`. The map
+  // must already have been populated, since saved games only record
+  // what's in each room, not how the rooms are connected. If the data
+  // is invalid, the game is left untouched.
+  pub fn restore_save_data(&mut self, data: &str) -> Result<(), String> {
+    let mut lines = data.lines();
+
+    match lines.next() {
+      Some(header) if header == SAVE_HEADER => {},
+      Some(header) if header.starts_with("werewolves-and-wanderer save") => {
+        return Err(String::from("This saved game is from a different \
+                                 version of the game."));
+      },
+      _ => { return Err(String::from("This is not a saved game.")); }
+    }
+
+    // The restored state is only used to hold values until we know
+    // they're all valid, so it never needs to talk to the player.
+    let mut restored = GameState::new(self.rng.seed(), self.map.clone(),
+                                      Box::new(ScriptedPlatform::new()));
+    let mut seed = self.rng.seed();
+    let mut rng_state = self.rng.state();
+    let mut has_fingerprint = false;
+
+    for room_id in self.map.room_ids() {
+      restored.map.mut_room(room_id).contents = None;
+    }
+    restored.map.reset_exits();
+
+    for line in lines.filter(|line| !line.is_empty()) {
+      let (field, value) = match line.find(' ') {
+        Some(i) => (&line[..i], &line[i + 1..]),
+        None => (line, ""),
+      };
+      match field {
+        "map" => {
+          let fingerprint = u64::from_str_radix(value, 16).map_err(|_| {
+            format!("Invalid value for '{}': {:?}", field, value)
+          })?;
+          if fingerprint != self.map.fingerprint() {
+            return Err(String::from("This saved game is from a different \
+                                     castle."));
+          }
+          has_fingerprint = true;
+        },
+        "name" => { restored.player_name = String::from(value) },
+        "seed" => { seed = parse(field, value)? },
+        "rng_state" => { rng_state = parse(field, value)? },
+        "strength" => { restored.strength = parse(field, value)? },
+        "wealth" => { restored.wealth = parse(field, value)? },
+        "tally" => { restored.tally = parse(field, value)? },
+        "monsters_killed" => {
+          restored.monsters_killed = parse(field, value)?
+        },
+        "room" => {
+          restored.curr_room = parse_room_id(&self.map, value)?
+        },
+        "shown_hint" => { restored.shown_hint = parse(field, value)? },
+        "items" => {
+          let quantities: Vec<&str> = value.split_whitespace().collect();
+          if quantities.len() != self.map.catalogue.len() {
+            return Err(String::from("Invalid number of items."));
+          }
+          for (id, quantity) in self.map.catalogue.ids().zip(quantities) {
+            restored.items.set_quantity(id, parse(field, quantity)?);
+          }
+        },
+        "visited" => {
+          for index in value.split_whitespace() {
+            restored.explored.visit(parse_room_id(&self.map, index)?);
+          }
+        },
+        "seen_exits" => {
+          let args: Vec<&str> = value.split_whitespace().collect();
+          let (room_id, dirs) = parse_exits(&self.map, field, &args)?;
+          for dir in dirs {
+            restored.explored.see_exit(room_id, dir);
+          }
+        },
+        "unlocked" | "found" => {
+          let args: Vec<&str> = value.split_whitespace().collect();
+          let (room_id, dirs) = parse_exits(&self.map, field, &args)?;
+          for dir in dirs {
+            let exit = restored.map.room(room_id).exit(dir).ok_or_else(|| {
+              format!("Room {} has no exit {}", room_id.0, dir.character())
+            })?;
+            if field == "unlocked" && exit.lock.is_some() {
+              restored.map.unlock_exit(room_id, dir);
+            } else if field == "found" && exit.secret {
+              restored.map.reveal_exit(room_id, dir);
+            } else {
+              return Err(format!("Invalid value for '{}': {:?}", field,
+                                 value));
+            }
+          }
+        },
+        "facts" => {
+          for name in value.split_whitespace() {
+            restored.achievements.learn(Fact::from_name(name).ok_or_else(|| {
+              format!("Unknown fact: {}", name)
+            })?);
+          }
+        },
+        "entered" => {
+          for index in value.split_whitespace() {
+            restored.achievements.enter(parse_room_id(&self.map, index)?);
+          }
+        },
+        "contents" => {
+          let args: Vec<&str> = value.split_whitespace().collect();
+          let (room_id, contents) = parse_contents(&self.map, &args)?;
+          restored.map.mut_room(room_id).contents = Some(contents);
+        },
+        _ => { return Err(format!("Unknown field: {}", field)); }
+      }
+    }
+
+    if !has_fingerprint {
+      return Err(String::from("The saved game doesn't say which castle \
+                               it's from."));
+    }
+    if restored.player_name.is_empty() {
+      return Err(String::from("The saved game has no player name."));
+    }
+
+    self.player_name = restored.player_name;
+    self.strength = restored.strength;
+    self.wealth = restored.wealth;
+    self.tally = restored.tally;
+    self.monsters_killed = restored.monsters_killed;
+    self.curr_room = restored.curr_room;
+    // The player already went through whatever happens on entering the
+    // room they saved in.
+    self.pending_triggers = false;
+    self.shown_hint = restored.shown_hint;
+    self.items = restored.items;
+    self.explored = restored.explored;
+    self.achievements = restored.achievements;
+    self.map = restored.map;
+    self.rng = Rng::restore(seed, rng_state);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use map::RoomContents::*;
+  use direction::Direction::{North, South};
+  use game_state::GameMode;
+  use platform::AbstractPlatform;
+  use platform::scripted::*;
+  use super::SAVE_KEY;
+  use achievements::Fact;
+
+  #[test]
+  fn test_save_and_restore_round_trip() {
+    let (mut state, _) = new_empty_game();
+    state.player_name = String::from("Alice Smith");
+    state.strength = 42;
+    state.wealth = 3;
+    state.tally = 17;
+    state.monsters_killed = 2;
+    let kitchen = state.map.find_room("Kitchen").unwrap();
+    let dungeon = state.map.find_room("Dungeon").unwrap();
+    let treasury = state.map.find_room("Treasury").unwrap();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    state.curr_room = kitchen;
+    let sword = give_item(&mut state, "sword", 1);
+    give_item(&mut state, "food", 7);
+    state.map.mut_room(dungeon).contents = Some(Treasure(99));
+    let dragon = state.map.monsters.find("dragon").unwrap();
+    state.map.mut_room(treasury).contents = Some(Terror(dragon));
+    state.rng.random();
+    state.explored.visit(kitchen);
+    state.explored.see_exit(kitchen, South);
+    state.map.lock_exit(kitchen, South, "sword");
+    state.map.unlock_exit(kitchen, South);
+    let bedroom = state.map.find_room("ChambermaidsBedroom").unwrap();
+    state.map.reveal_exit(bedroom, North);
+    state.achievements.learn(Fact::HasAxe);
+    state.achievements.enter(kitchen);
+
+    let data = state.to_save_data();
+    let (mut restored, _) = new_empty_game();
+    restored.map.mut_room(hallway).contents = Some(Treasure(1));
+    restored.map.lock_exit(kitchen, South, "sword");
+    restored.restore_save_data(&data).unwrap();
+
+    assert_eq!(restored.to_save_data(), data);
+    assert_eq!(restored.player_name, "Alice Smith");
+    assert_eq!(restored.curr_room, kitchen);
+    assert!(restored.items.owns(sword));
+    assert!(restored.map.room(hallway).contents.is_none());
+    assert_eq!(restored.map.room(treasury).contents, Some(Terror(dragon)));
+    assert_eq!(restored.rng.random(), state.rng.random());
+    assert_eq!(restored.map.room(hallway).name, "Hallway");
+    assert_eq!(restored.explored, state.explored);
+    assert_eq!(restored.achievements, state.achievements);
+    assert!(restored.map.room(bedroom).get_exit(North).is_some());
+  }
+
+  #[test]
+  fn test_restoring_invalid_data_fails() {
+    let (mut state, _) = new_empty_game();
+    state.player_name = String::from("Alice");
+    let data = state.to_save_data();
+
+    assert!(state.restore_save_data("blah").is_err());
+    assert!(state.restore_save_data(
+      &data.replace("save 5", "save 4")).is_err());
+    assert!(state.restore_save_data(
+      &data.replace("strength 100", "strength lots")).is_err());
+    assert!(state.restore_save_data(
+      &data.replace("room 5", "room 500")).is_err());
+    assert!(state.restore_save_data(
+      &format!("{}seen_exits 5 x\n", data)).is_err());
+    assert!(state.restore_save_data(
+      &format!("{}found 0 n\n", data)).is_err());
+    assert!(state.restore_save_data(
+      &format!("{}facts flew\n", data)).is_err());
+    assert!(state.restore_save_data(&data.replace("map ", "map x")).is_err());
+  }
+
+  #[test]
+  fn test_saves_only_resume_on_the_same_map() {
+    let (mut state, _) = new_empty_game();
+    state.player_name = String::from("Alice");
+    let data = state.to_save_data();
+    let (mut other, _) = new_empty_game();
+    let hallway = other.map.find_room("Hallway").unwrap();
+    other.map.hide_exit(hallway, South);
+
+    assert_eq!(other.restore_save_data(&data).err(),
+               Some(String::from("This saved game is from a different \
+                                  castle.")));
+    let unmarked: Vec<&str> = data.lines().filter(|line| {
+      !line.starts_with("map ")
+    }).collect();
+    assert!(state.restore_save_data(&unmarked.join("\n")).is_err());
+    assert_eq!(state.restore_save_data(&data), Ok(()));
+  }
+
+  #[test]
+  fn test_saving_and_resuming_a_game() {
+    let (mut state, script) = new_empty_game();
+    script.push_inputs(&["Alice", "e", "v"]);
+    script.run_until_blocked(&mut state);
+    assert!(script.take_output()
+      .contains("Your game has been saved."));
+    let data = state.platform.load_data(SAVE_KEY).unwrap();
+
+    let (mut resumed, mut resumed_script) = new_empty_game();
+    resumed_script.save_data(SAVE_KEY, &data).unwrap();
+    resumed_script.push_inputs(&["y"]);
+    resumed_script.run_until_blocked(&mut resumed);
+
+    assert!(resumed_script.take_output()
+      .contains("Welcome back, Alice."));
+    assert_eq!(resumed.curr_mode, GameMode::Primary);
+    assert_eq!(resumed.curr_room, resumed.map.find_room("Hallway").unwrap());
+    assert_eq!(resumed.strength, 95);
+  }
+
+  #[test]
+  fn test_declining_to_resume_starts_a_new_game() {
+    let (mut state, mut script) = new_empty_game();
+    script.save_data(SAVE_KEY, "garbage").unwrap();
+    script.push_inputs(&["n", "Bob"]);
+    script.run_until_blocked(&mut state);
+
+    assert_eq!(state.player_name, "Bob");
+    assert_eq!(state.curr_mode, GameMode::Primary);
+  }
+}
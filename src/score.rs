@@ -0,0 +1,134 @@
+use high_scores::Outcome;
+use game_state::GameState;
+
+const TALLY_MULTIPLIER: i32 = 3;
+const STRENGTH_MULTIPLIER: i32 = 5;
+const WEALTH_MULTIPLIER: i32 = 2;
+const FOOD_MULTIPLIER: i32 = 1;
+const MONSTERS_KILLED_MULTIPLIER: i32 = 30;
+const ESCAPE_BONUS: i32 = 100;
+
+// Part of the score that's based on some quantity, e.g. the player's
+// strength.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScoreComponent {
+  pub desc: &'static str,
+  pub amount: i32,
+  pub multiplier: i32,
+}
+
+impl ScoreComponent {
+  pub fn points(&self) -> i32 {
+    self.amount * self.multiplier
+  }
+}
+
+// A fixed number of points awarded for an accomplishment, e.g. getting
+// out of the castle.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScoreBonus {
+  pub desc: &'static str,
+  pub points: i32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScoreBreakdown {
+  pub components: Vec<ScoreComponent>,
+  pub bonuses: Vec<ScoreBonus>,
+}
+
+impl ScoreBreakdown {
+  pub fn total(&self) -> i32 {
+    self.components.iter().map(|c| c.points()).sum::<i32>() +
+    self.bonuses.iter().map(|b| b.points).sum::<i32>()
+  }
+
+  // Returns the breakdown as lines of text, ending with the total.
+  pub fn describe(&self) -> Vec<String> {
+    let mut lines = self.components.iter().map(|c| {
+      format!("  {:<16} {:>5} x {:<2} = {:>5}", c.desc, c.amount,
+              c.multiplier, c.points())
+    }).collect::<Vec<_>>();
+    for bonus in self.bonuses.iter() {
+      lines.push(format!("  {:<27} = {:>5}", bonus.desc, bonus.points));
+    }
+    lines.push(format!("  {:<27}   {:>5}", "", "-----"));
+    lines.push(format!("  {:<27}   {:>5}", "Total", self.total()));
+    lines
+  }
+}
+
+impl GameState {
+  // Returns how the player's score is made up. Bonuses depend on how
+  // the game ended, if it has.
+  pub fn get_score_breakdown(&self,
+                             outcome: Option<Outcome>) -> ScoreBreakdown {
+    let component = |desc, amount, multiplier| {
+      ScoreComponent { desc, amount, multiplier }
+    };
+    let mut bonuses = Vec::new();
+    if outcome == Some(Outcome::Escaped) {
+      bonuses.push(ScoreBonus {
+        desc: "Escaped the castle",
+        points: ESCAPE_BONUS,
+      });
+    }
+    ScoreBreakdown {
+      components: vec![
+        component("Tally", self.tally, TALLY_MULTIPLIER),
+        component("Strength", self.strength, STRENGTH_MULTIPLIER),
+        component("Wealth", self.wealth, WEALTH_MULTIPLIER),
+        component("Food", self.food(), FOOD_MULTIPLIER),
+        component("Monsters killed", self.monsters_killed,
+                  MONSTERS_KILLED_MULTIPLIER),
+      ],
+      bonuses,
+    }
+  }
+
+  pub fn print_score_breakdown(&mut self, breakdown: &ScoreBreakdown) {
+    wrapln!(self, "Your score is made up of:\n");
+    for line in breakdown.describe() {
+      wrapln!(self, "{}", line);
+    }
+    wrapln!(self);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_score_breakdown() {
+    let (mut state, _) = new_empty_game();
+    state.tally = 3;
+    state.strength = 85;
+    state.wealth = 10;
+    state.monsters_killed = 1;
+    give_item(&mut state, "food", 4);
+
+    let breakdown = state.get_score_breakdown(None);
+    assert_eq!(breakdown.components[1], ScoreComponent {
+      desc: "Strength",
+      amount: 85,
+      multiplier: 5,
+    });
+    assert!(breakdown.bonuses.is_empty());
+    assert_eq!(breakdown.total(), 9 + 425 + 20 + 4 + 30);
+
+    let escaped = state.get_score_breakdown(Some(Outcome::Escaped));
+    assert_eq!(escaped.total(), breakdown.total() + ESCAPE_BONUS);
+    assert_eq!(escaped.describe(), vec![
+      "  Tally                3 x 3  =     9",
+      "  Strength            85 x 5  =   425",
+      "  Wealth              10 x 2  =    20",
+      "  Food                 4 x 1  =     4",
+      "  Monsters killed      1 x 30 =    30",
+      "  Escaped the castle          =   100",
+      "                                -----",
+      "  Total                           588",
+    ]);
+  }
+}
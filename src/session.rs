@@ -0,0 +1,275 @@
+use std::fmt;
+
+use game_state::GameState;
+use map::Map;
+use command::ParserMode;
+use platform::ProcessPlatform;
+use platform::scripted::ScriptedPlatform;
+
+// The first line of every session file. The number at the end should be
+// bumped whenever the format changes.
+const SESSION_HEADER: &str = "werewolves-and-wanderer session 2";
+
+// A session is a record of everything the player typed and everything
+// the game printed during a single game, along with the random seed
+// the game was started with, the parser it used and the fingerprint of
+// its map. Since those determine everything else, replaying the
+// player's input against a game with the same ones should always
+// produce exactly the same output.
+//
+// Sessions are stored as plain text, e.g.:
+//
+//   werewolves-and-wanderer session 2
+//   seed 1234
+//   parser classic
+//   map 8c3f0e2a51d4b907
+//   > Alice
+//   < Alice, your strength is 100.
+//   <
+//   > e
+//
+// Lines starting with ">" are input, while lines starting with "<"
+// are output.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Session {
+  pub seed: u64,
+  pub parser: ParserMode,
+  pub map: u64,
+  pub entries: Vec<SessionEntry>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SessionEntry {
+  Input(String),
+  Output(String),
+}
+
+impl Session {
+  pub fn new(seed: u64, parser: ParserMode, map: &Map) -> Self {
+    Self { seed, parser, map: map.fingerprint(), entries: Vec::new() }
+  }
+
+  // Returns a session played in the same way as this one, but with
+  // nothing in it yet.
+  pub fn empty_copy(&self) -> Self {
+    Self { entries: Vec::new(), ..*self }
+  }
+
+  pub fn record_input<T: AsRef<str>>(&mut self, input: T) {
+    self.entries.push(SessionEntry::Input(String::from(input.as_ref())));
+  }
+
+  // Output is recorded one line at a time, so that it doesn't matter
+  // whether the game printed two lines at once or one after another.
+  pub fn record_output<T: AsRef<str>>(&mut self, output: T) {
+    for line in output.as_ref().split('\n') {
+      self.entries.push(SessionEntry::Output(String::from(line)));
+    }
+  }
+
+  pub fn inputs(&self) -> Vec<String> {
+    self.entries.iter().filter_map(|entry| match *entry {
+      SessionEntry::Input(ref input) => Some(input.clone()),
+      SessionEntry::Output(_) => None,
+    }).collect()
+  }
+
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut lines = data.lines();
+
+    if lines.next() != Some(SESSION_HEADER) {
+      return Err(String::from("This is not a session file."));
+    }
+
+    let mut field = |name: &str| {
+      lines.next().and_then(|line| line.strip_prefix(name))
+        .and_then(|rest| rest.strip_prefix(' '))
+        .ok_or_else(|| format!("The session has no {}.", name))
+    };
+    let seed = field("seed")?;
+    let seed = seed.parse::<u64>().map_err(|_| {
+      format!("Invalid seed: {:?}", seed)
+    })?;
+    let parser = match field("parser")? {
+      "classic" => ParserMode::Classic,
+      "words" => ParserMode::Words,
+      other => { return Err(format!("Invalid parser: {:?}", other)); }
+    };
+    let map = field("map")?;
+    let map = u64::from_str_radix(map, 16).map_err(|_| {
+      format!("Invalid map: {:?}", map)
+    })?;
+
+    let mut session = Self { seed, parser, map, entries: Vec::new() };
+    for (i, line) in lines.enumerate() {
+      if let Some(text) = line.strip_prefix('>') {
+        session.record_input(entry_text(text));
+      } else if let Some(text) = line.strip_prefix('<') {
+        session.record_output(entry_text(text));
+      } else {
+        return Err(format!("Invalid session entry on line {}: {:?}",
+                           i + 5, line));
+      }
+    }
+    Ok(session)
+  }
+
+  // Re-runs the session's input against a new game with the session's
+  // seed and parser, and makes sure it produces exactly the same
+  // output. Sessions only record the map's fingerprint, so it's up to
+  // the caller to provide the map itself.
+  pub fn replay(&self, map: Map) -> Result<(), String> {
+    if map.fingerprint() != self.map {
+      return Err(String::from("This session was played in a different \
+                               castle."));
+    }
+    let mut state = GameState::new(self.seed, map,
+                                   Box::new(ScriptedPlatform::new()));
+    state.platform.start_replay(self);
+    state.parser = self.parser;
+    state.map.populate(&mut state.rng);
+
+    while !state.is_finished() {
+      if state.is_waiting_for_input() && !state.platform.has_replay_input() {
+        break;
+      }
+      state.tick();
+    }
+
+    let replayed = state.platform.finish_replay();
+
+    for (i, (expected, actual)) in self.entries.iter()
+                                     .zip(replayed.entries.iter())
+                                     .enumerate() {
+      if expected != actual {
+        return Err(format!("Entry {} differs: expected {:?} but got {:?}.",
+                           i + 1, expected, actual));
+      }
+    }
+    if self.entries.len() != replayed.entries.len() {
+      return Err(format!("Expected {} entries but got {}.",
+                         self.entries.len(), replayed.entries.len()));
+    }
+    Ok(())
+  }
+}
+
+// The text of an entry follows its ">" or "<" after a single space,
+// which is left out when the text is empty.
+fn entry_text(rest: &str) -> &str {
+  rest.strip_prefix(' ').unwrap_or(rest)
+}
+
+impl fmt::Display for Session {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{}", SESSION_HEADER)?;
+    writeln!(f, "seed {}", self.seed)?;
+    writeln!(f, "parser {}", match self.parser {
+      ParserMode::Classic => "classic",
+      ParserMode::Words => "words",
+    })?;
+    writeln!(f, "map {:016x}", self.map)?;
+    for entry in self.entries.iter() {
+      let (prefix, text) = match *entry {
+        SessionEntry::Input(ref text) => (">", text),
+        SessionEntry::Output(ref text) => ("<", text),
+      };
+      if text.is_empty() {
+        writeln!(f, "{}", prefix)?;
+      } else {
+        writeln!(f, "{} {}", prefix, text)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use map_gen::CastlePlan;
+  use platform::scripted::*;
+
+  fn record_game(parser: ParserMode, inputs: &[&str]) -> Session {
+    let script = ScriptedPlatform::new();
+    let mut state = GameState::new(42, Map::castle(),
+                                   Box::new(script.clone()));
+    state.parser = parser;
+    let session = Session::new(42, parser, &state.map);
+    state.platform.start_recording(session);
+    state.map.populate(&mut state.rng);
+    script.push_inputs(inputs);
+    script.run_until_blocked(&mut state);
+
+    state.platform.stop_recording().unwrap()
+  }
+
+  #[test]
+  fn test_session_round_trips_through_text() {
+    let mut session = Session::new(5, ParserMode::Words, &Map::castle());
+    session.record_output("Hello\n");
+    session.record_input("Alice");
+    session.record_output("Bye");
+
+    assert_eq!(Session::parse(&session.to_string()), Ok(session));
+  }
+
+  #[test]
+  fn test_parsing_invalid_sessions_fails() {
+    let header = "werewolves-and-wanderer session 2";
+    let session = |rest: &str| Session::parse(&format!("{}\n{}", header,
+                                                        rest));
+    let start = "seed 1\nparser classic\nmap 00000000000000ff";
+
+    assert!(Session::parse("blah").is_err());
+    assert!(Session::parse(
+      "werewolves-and-wanderer session 1\nseed 1\n> Alice").is_err());
+    assert!(session("seed x").is_err());
+    assert!(session(start).is_ok());
+    assert_eq!(session("seed 1\nmap 00000000000000ff").err(),
+               Some(String::from("The session has no parser.")));
+    assert!(session(&start.replace("classic", "fancy")).is_err());
+    assert!(session(&start.replace("map 0", "map x")).is_err());
+    assert!(session(&format!("{}\n? huh", start)).is_err());
+    assert!(session(&format!("{}\n€x", start)).is_err());
+  }
+
+  #[test]
+  fn test_recorded_session_replays() {
+    let session = record_game(ParserMode::Classic,
+                              &["Alice", "e", "w", "i", "4", "2", "0", "q"]);
+
+    assert_eq!(session.inputs().len(), 8);
+    assert!(session.entries.contains(
+      &SessionEntry::Output(String::from("You bought 2 units of food."))));
+    assert_eq!(session.replay(Map::castle()), Ok(()));
+  }
+
+  #[test]
+  fn test_replay_uses_the_recorded_parser() {
+    let session = record_game(ParserMode::Words,
+                              &["Alice", "shop", "buy 2 food", "done", "q"]);
+    let session = Session::parse(&session.to_string()).unwrap();
+
+    assert_eq!(session.parser, ParserMode::Words);
+    assert_eq!(session.replay(Map::castle()), Ok(()));
+  }
+
+  #[test]
+  fn test_replay_refuses_a_different_map() {
+    let session = record_game(ParserMode::Classic, &["Alice", "q"]);
+    let map = CastlePlan::default().generate(3).unwrap();
+
+    assert_eq!(session.replay(map).err(),
+               Some(String::from("This session was played in a different \
+                                  castle.")));
+  }
+
+  #[test]
+  fn test_replay_detects_different_output() {
+    let mut session = record_game(ParserMode::Classic, &["Alice", "q"]);
+    session.record_output("This never happened.");
+
+    assert!(session.replay(Map::castle()).is_err());
+  }
+}
@@ -1,29 +1,33 @@
 use std::marker::PhantomData;
-use enum_primitive::FromPrimitive;
 
-use platform::*;
+use rng::Rng;
+
+pub use sized_enum_derive::SizedEnum;
 
 // There doesn't seem to be a convenient way to get the "size" or
 // "range" of an enum's possible values, so we'll make a trait for
 // that here.
 //
-// Ideally we could populate it automatically through a macro, but
-// for now we'll just implement it manually for all our enums.
-pub trait SizedEnum : FromPrimitive {
+// It's meant to be derived with `#[derive(SizedEnum)]`, which numbers
+// the variants in the order they're declared, so there's no constant
+// to keep in sync when a variant is added.
+pub trait SizedEnum : Sized + Copy {
   fn size() -> usize;
 
+  fn from_usize(index: usize) -> Option<Self>;
+
+  fn to_usize(self) -> usize;
+
+  // The name of the variant as it's written in the code, e.g. "North".
+  fn name(self) -> &'static str;
+
   fn iter() -> SizedEnumIterator<Self> {
     SizedEnumIterator::new()
   }
 
-  fn random() -> Self {
-    loop {
-      let r = Platform::random_i32(0, Self::size() as i32);
-      match Self::from_i32(r) {
-        Some(t) => { return t; },
-        None => {}
-      }
-    }
+  fn random(rng: &mut Rng) -> Self {
+    let r = rng.random_i32(0, Self::size() as i32);
+    Self::from_usize(r as usize).expect("Random index is out of range")
   }
 }
 
@@ -42,11 +46,38 @@ impl<T: SizedEnum> Iterator for SizedEnumIterator<T> {
   type Item = T;
 
   fn next(&mut self) -> Option<T> {
-    while self.current < T::size() {
-      let result = T::from_usize(self.current);
+    let result = T::from_usize(self.current);
+    if result.is_some() {
       self.current += 1;
-      if result.is_some() { return result; }
     }
-    None
+    result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, PartialEq, Copy, Clone, SizedEnum)]
+  enum Suit {
+    Hearts,
+    /// Doc comments on variants are fine.
+    Spades,
+    Clubs,
+  }
+
+  #[test]
+  fn test_derived_sized_enum() {
+    assert_eq!(Suit::size(), 3);
+    assert_eq!(Suit::iter().collect::<Vec<_>>(),
+               vec![Suit::Hearts, Suit::Spades, Suit::Clubs]);
+    assert_eq!(Suit::from_usize(1), Some(Suit::Spades));
+    assert_eq!(Suit::from_usize(3), None);
+    assert_eq!(Suit::Clubs.to_usize(), 2);
+    assert_eq!(Suit::Spades.name(), "Spades");
+
+    let mut rng = Rng::new(1);
+    rng.queue(&[0.99]);
+    assert_eq!(Suit::random(&mut rng), Suit::Clubs);
   }
 }
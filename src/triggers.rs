@@ -0,0 +1,148 @@
+use map::RoomId;
+use events::GameEvent;
+use game_state::GameState;
+use high_scores::Outcome;
+
+// Something that happens as soon as the player enters a room, before
+// the room is described.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerAction {
+  // Tells the player something, then pauses for effect.
+  Say(String),
+
+  // Takes the player to another room, like the castle's lift.
+  Teleport(RoomId),
+
+  // The player has made it out of the castle and wins the game.
+  Escape,
+
+  // Costs the player this much strength.
+  Damage(i32),
+
+  // Gives the player some units of the item with the given key. Items
+  // are looked up by key when the trigger fires, since the shop's
+  // catalogue can be replaced after the map is loaded.
+  Give(String, i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+  pub action: TriggerAction,
+
+  // Whether the trigger only fires the first time the player enters
+  // the room.
+  pub once: bool,
+}
+
+impl Trigger {
+  pub fn new(action: TriggerAction) -> Self {
+    Self { action, once: false }
+  }
+
+  pub fn once(action: TriggerAction) -> Self {
+    Self { action, once: true }
+  }
+
+  // Whether firing the trigger takes the player out of the room for
+  // good, so that nothing can ever be left in it.
+  pub fn is_transient(&self) -> bool {
+    match self.action {
+      TriggerAction::Teleport(_) | TriggerAction::Escape => !self.once,
+      _ => false,
+    }
+  }
+}
+
+impl GameState {
+  // Fires the triggers of the room the player just entered, in the
+  // order they were declared. Returns false if one of them took the
+  // player elsewhere or ended the game, in which case the room
+  // shouldn't be described.
+  pub fn fire_triggers(&mut self, first_visit: bool) -> bool {
+    let triggers = self.map.room(self.curr_room).triggers.clone();
+    for trigger in triggers.into_iter().filter(|t| first_visit || !t.once) {
+      match trigger.action {
+        TriggerAction::Say(text) => {
+          wrapln!(self, "{}", text);
+          self.pause();
+        },
+        TriggerAction::Teleport(destination) => {
+          self.enter_room(destination);
+          return false;
+        },
+        TriggerAction::Escape => {
+          self.escape();
+          return false;
+        },
+        TriggerAction::Damage(amount) => {
+          self.strength -= amount;
+          if self.strength < 1 {
+            return false;
+          }
+        },
+        TriggerAction::Give(key, quantity) => {
+          if let Some(id) = self.map.catalogue.find(&key) {
+            if self.map.catalogue.get(id).stackable {
+              self.items.increase(id, quantity);
+            } else {
+              self.items.obtain(id);
+            }
+            self.emit(GameEvent::ItemFound(id, quantity));
+          }
+        },
+      }
+    }
+    true
+  }
+
+  fn escape(&mut self) {
+    wrapln!(self, "\nYou've done it!!");
+    self.pause();
+    wrapln!(self, "That was the exit from the castle.");
+    self.pause();
+    wrapln!(self, "\nYou have succeeded, {}!", self.player_name);
+    wrapln!(self, "\nYou managed to get out of the castle.");
+    self.pause();
+    wrapln!(self, "\nWell done!");
+    self.pause();
+    self.emit(GameEvent::Escaped);
+    self.finish_game(Outcome::Escaped);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::TriggerAction::*;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_triggers_fire_when_entering_a_room() {
+    let (mut state, script) = new_empty_game();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    state.map.add_trigger(hallway, Trigger::new(Damage(30)));
+    state.map.add_trigger(hallway,
+                          Trigger::once(Say(String::from("A trap!"))));
+    state.map.add_trigger(hallway,
+                          Trigger::once(Give(String::from("food"), 2)));
+    script.push_inputs(&["Alice", "e", "w", "e"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert_eq!(output.matches("A trap!").count(), 1);
+    assert_eq!(state.strength, 100 - 3 * 5 - 2 * 30);
+    assert_eq!(state.food(), 2);
+  }
+
+  #[test]
+  fn test_deadly_triggers_kill() {
+    let (mut state, script) = new_empty_game();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    state.map.add_trigger(hallway, Trigger::new(Damage(500)));
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output().contains("You have died"));
+    assert!(state.is_finished());
+  }
+}
@@ -1,25 +1,58 @@
 pub fn friendly_join(strings: Vec<&str>) -> String {
+  friendly_join_with(strings, "and")
+}
+
+pub fn friendly_join_with(strings: Vec<&str>, conjunction: &str) -> String {
   match strings.len() {
     0 => String::new(),
     1 => String::from(strings[0]),
-    2 => format!("{} and {}", strings[0], strings[1]),
+    2 => format!("{} {} {}", strings[0], conjunction, strings[1]),
     _ => {
       let mut s = String::new();
-      for i in 0..strings.len() - 1 {
-        s.push_str(strings[i]);
+      for string in &strings[..strings.len() - 1] {
+        s.push_str(string);
         s.push_str(", ");
       }
-      s.push_str("and ");
+      s.push_str(conjunction);
+      s.push(' ');
       s.push_str(strings[strings.len() - 1]);
       s
     }
   }
 }
 
+// Converts a number of days since 1970-01-01 into a YYYY-MM-DD date,
+// using the proleptic Gregorian calendar. See
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+// for how this works.
+pub fn date_from_days(days: i64) -> String {
+  let z = days + 719_468;
+  let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+  let day_of_era = z - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 -
+                     day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 -
+                                  year_of_era / 100);
+  let mp = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
 #[test]
 fn test_friendly_join() {
   assert_eq!(friendly_join(vec![]), "");
   assert_eq!(friendly_join(vec!["foo"]), "foo");
   assert_eq!(friendly_join(vec!["foo", "bar"]), "foo and bar");
   assert_eq!(friendly_join(vec!["foo", "bar", "baz"]), "foo, bar, and baz");
+  assert_eq!(friendly_join_with(vec!["foo", "bar"], "or"), "foo or bar");
+}
+
+#[test]
+fn test_date_from_days() {
+  assert_eq!(date_from_days(0), "1970-01-01");
+  assert_eq!(date_from_days(59), "1970-03-01");
+  assert_eq!(date_from_days(11_016), "2000-02-29");
+  assert_eq!(date_from_days(-1), "1969-12-31");
 }
@@ -0,0 +1,166 @@
+use direction::Direction;
+use sized_enum::SizedEnum;
+use map::RoomId;
+use map::RoomContents::{Terror, Treasure};
+use events::GameEvent;
+use game_state::GameState;
+
+impl GameState {
+  // Gives every wandering monster a chance to move to a neighbouring
+  // room. Monsters only go through exits the player could use, and
+  // only into rooms that could have started out with a monster in
+  // them and are empty, apart from maybe the player and their
+  // treasure. A monster next to the player always heads for them, and
+  // once it's found them it stays put.
+  pub fn move_monsters(&mut self) {
+    let monsters: Vec<_> = self.map.room_ids().filter_map(|room_id| {
+      match self.map.room(room_id).contents {
+        Some(Terror(monster_id)) => Some((room_id, monster_id)),
+        _ => None,
+      }
+    }).collect();
+
+    for (room_id, monster_id) in monsters {
+      let chance = self.map.monsters.get(monster_id).wander_chance;
+      if room_id == self.curr_room || chance <= 0.0 ||
+         self.rng.random() >= chance {
+        continue;
+      }
+
+      let choices: Vec<RoomId> = Direction::iter().filter_map(|dir| {
+        self.map.room(room_id).get_open_exit(dir)
+      }).filter(|&next| {
+        self.map.can_hold_contents(next) &&
+          match self.map.room(next).contents {
+            None => true,
+            Some(Treasure(_)) => next == self.curr_room,
+            Some(Terror(_)) => false,
+          }
+      }).collect();
+      let next = if choices.contains(&self.curr_room) {
+        self.curr_room
+      } else if choices.is_empty() {
+        continue;
+      } else {
+        choices[self.rng.random_i32(0, choices.len() as i32) as usize]
+      };
+
+      if next == self.curr_room {
+        // The player grabs whatever treasure they're standing on
+        // before the monster can get to it.
+        if let Some(Treasure(amount)) = self.map.room(next).contents {
+          self.wealth += amount as i32;
+          self.emit(GameEvent::TreasurePickedUp(amount as i32));
+        }
+        // Describing the room again is what starts the fight.
+        self.show_desc = true;
+        self.ambushed = true;
+      }
+      self.map.mut_room(room_id).contents = None;
+      self.map.mut_room(next).contents = Some(Terror(monster_id));
+    }
+  }
+
+  // Tells the player about any monsters they can hear in the rooms
+  // next to theirs, which they can do even in the dark.
+  pub fn listen_for_monsters(&mut self) {
+    for dir in Direction::iter() {
+      let next = match self.map.room(self.curr_room).get_exit(dir) {
+        Some(next) => next,
+        None => continue,
+      };
+      if let Some(Terror(monster_id)) = self.map.room(next).contents {
+        let noise = self.map.monsters.get(monster_id).noise.clone();
+        if !noise.is_empty() {
+          wrapln!(self, "You hear {} {}.", noise, dir.toward());
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use map::RoomContents::{Terror, Treasure};
+  use game_state::GameMode;
+  use platform::scripted::*;
+
+  #[test]
+  fn test_monsters_can_be_heard_next_door() {
+    let (mut state, script) = new_empty_game();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.mut_room(hallway).contents = Some(Terror(werewolf));
+    script.push_inputs(&["Alice"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output().contains("You hear growling to the east."));
+  }
+
+  #[test]
+  fn test_wandering_monsters_follow_the_player() {
+    let (mut state, script) = new_empty_game();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    let chamber = state.map.find_room("AudienceChamber").unwrap();
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.monsters.get_mut(werewolf).wander_chance = 1.0;
+    state.map.mut_room(chamber).contents = Some(Terror(werewolf));
+    give_item(&mut state, "torch", 1);
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output().contains("It is a ferocious werewolf!"));
+    assert_eq!(state.map.room(hallway).contents, Some(Terror(werewolf)));
+    assert_eq!(state.map.room(chamber).contents, None);
+  }
+
+  #[test]
+  fn test_monsters_follow_the_player_onto_treasure() {
+    let (mut state, script) = new_empty_game();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    let chamber = state.map.find_room("AudienceChamber").unwrap();
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.monsters.get_mut(werewolf).wander_chance = 1.0;
+    state.map.mut_room(chamber).contents = Some(Terror(werewolf));
+    state.map.mut_room(hallway).contents = Some(Treasure(30));
+    give_item(&mut state, "torch", 1);
+    let wealth = state.wealth;
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+
+    assert!(script.take_output().contains("It is a ferocious werewolf!"));
+    assert_eq!(state.map.room(hallway).contents, Some(Terror(werewolf)));
+    assert_eq!(state.wealth, wealth + 30);
+  }
+
+  #[test]
+  fn test_monsters_attack_in_the_dark() {
+    let (mut state, script) = new_empty_game();
+    let chamber = state.map.find_room("AudienceChamber").unwrap();
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.monsters.get_mut(werewolf).wander_chance = 1.0;
+    state.map.mut_room(chamber).contents = Some(Terror(werewolf));
+    script.push_inputs(&["Alice", "e"]);
+    script.run_until_blocked(&mut state);
+
+    let output = script.take_output();
+    assert!(output.contains("It is too dark to see anything."));
+    assert!(output.contains("It is a ferocious werewolf!"));
+    assert!(matches!(state.curr_mode, GameMode::Combat(_)));
+  }
+
+  #[test]
+  fn test_monsters_stay_out_of_the_entrance() {
+    let (mut state, script) = new_empty_game();
+    let hallway = state.map.find_room("Hallway").unwrap();
+    let werewolf = state.map.monsters.find("werewolf").unwrap();
+    state.map.monsters.get_mut(werewolf).wander_chance = 1.0;
+    state.map.mut_room(hallway).contents = Some(Terror(werewolf));
+    script.push_inputs(&["Alice"]);
+    script.run_until_blocked(&mut state);
+
+    state.move_monsters();
+    assert_eq!(state.map.room(state.map.entrance).contents, None);
+    assert!(state.map.room(hallway).contents.is_none());
+  }
+}
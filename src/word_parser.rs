@@ -0,0 +1,104 @@
+// The original BASIC program only ever looked at the first letter of
+// whatever the player typed. This module implements an alternative
+// that understands whole words, e.g. "go north" or "consume 3 food".
+
+// Words that mean the same thing as another word. Commands' phrases
+// only need to be written using the word on the right.
+const SYNONYMS: [(&str, &str); 12] = [
+  ("walk", "go"),
+  ("move", "go"),
+  ("head", "go"),
+  ("run", "flee"),
+  ("consume", "eat"),
+  ("devour", "eat"),
+  ("take", "get"),
+  ("grab", "get"),
+  ("purchase", "buy"),
+  ("examine", "look"),
+  ("gold", "treasure"),
+  ("money", "treasure"),
+];
+
+// Words that don't change the meaning of a command, so they're
+// ignored entirely.
+const FILLER_WORDS: [&str; 10] = [
+  "a", "an", "the", "some", "of", "to", "my", "unit", "units", "please",
+];
+
+#[derive(Debug, PartialEq)]
+pub struct Tokens {
+  pub words: Vec<String>,
+  pub amount: Option<i32>,
+}
+
+fn canonicalize(word: &str) -> String {
+  for &(synonym, canonical) in SYNONYMS.iter() {
+    if word == synonym {
+      return String::from(canonical);
+    }
+  }
+  String::from(word)
+}
+
+// Splits the player's input into lowercase words, replacing synonyms
+// and dropping filler words along the way. A number anywhere in the
+// input is treated as the amount the command applies to.
+pub fn tokenize(input: &str) -> Result<Tokens, String> {
+  let mut tokens = Tokens { words: Vec::new(), amount: None };
+
+  for word in input.split_whitespace() {
+    let word = word.to_lowercase();
+    if let Ok(amount) = word.parse::<i32>() {
+      if tokens.amount.is_some() {
+        return Err(String::from("I can only handle one number at a time."));
+      }
+      tokens.amount = Some(amount);
+    } else if !FILLER_WORDS.contains(&word.as_str()) {
+      tokens.words.push(canonicalize(&word));
+    }
+  }
+
+  Ok(tokens)
+}
+
+// Returns whether the given words match the phrase. If `allow_prefixes`
+// is true, each word only needs to be the start of the corresponding
+// word in the phrase, so e.g. "ge tr" matches "get treasure".
+pub fn matches_phrase(words: &[String], phrase: &str,
+                      allow_prefixes: bool) -> bool {
+  let phrase_words: Vec<&str> = phrase.split_whitespace().collect();
+
+  words.len() == phrase_words.len() &&
+    words.iter().zip(phrase_words).all(|(word, phrase_word)| {
+      if allow_prefixes {
+        phrase_word.starts_with(word.as_str())
+      } else {
+        word == phrase_word
+      }
+    })
+}
+
+#[test]
+fn test_tokenize() {
+  let words = |input| tokenize(input).unwrap().words;
+
+  assert_eq!(tokenize("Consume 3 units of food"), Ok(Tokens {
+    words: vec![String::from("eat"), String::from("food")],
+    amount: Some(3),
+  }));
+  assert_eq!(words("  walk   North "), vec!["go", "north"]);
+  assert_eq!(words("buy the sword"), vec!["buy", "sword"]);
+  assert!(tokenize("buy 3 food 4").is_err());
+}
+
+#[test]
+fn test_matches_phrase() {
+  let words = |input| tokenize(input).unwrap().words;
+
+  assert!(matches_phrase(&words("eat food"), "eat food", false));
+  assert!(!matches_phrase(&words("eat"), "eat food", false));
+  assert!(!matches_phrase(&words("ea"), "eat", false));
+  assert!(matches_phrase(&words("ea"), "eat", true));
+  assert!(matches_phrase(&words("ge tr"), "get treasure", true));
+  assert!(!matches_phrase(&words("pi tr"), "pick up treasure", true));
+}